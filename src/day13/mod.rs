@@ -2,6 +2,8 @@
 
 use std::{cmp::Ordering, collections::VecDeque, fs};
 
+use serde_json::Value;
+
 use nom::{
     branch::alt,
     bytes::complete::tag,
@@ -41,6 +43,66 @@ fn parse(input: &str) -> IResult<&str, Vec<Pair>> {
     )(input)
 }
 
+#[derive(Debug)]
+enum JsonError {
+    Syntax(serde_json::Error),
+    NotAnInteger(Value),
+    Unsupported(Value),
+    Unpaired,
+}
+
+impl TryFrom<&Value> for Packet {
+    type Error = JsonError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Array(items) => items
+                .iter()
+                .map(Packet::try_from)
+                .collect::<Result<VecDeque<_>, _>>()
+                .map(Packet::List),
+            Value::Number(num) => num
+                .as_u64()
+                .and_then(|num| u32::try_from(num).ok())
+                .map(Packet::Number)
+                .ok_or_else(|| JsonError::NotAnInteger(value.clone())),
+            _ => Err(JsonError::Unsupported(value.clone())),
+        }
+    }
+}
+
+impl From<&Packet> for Value {
+    fn from(packet: &Packet) -> Self {
+        match packet {
+            Packet::List(items) => Value::Array(items.iter().map(Value::from).collect()),
+            Packet::Number(num) => Value::from(*num),
+        }
+    }
+}
+
+// alternative to the nom parser, treating every non-empty line as a json document
+fn parse_json(input: &str) -> Result<Vec<Pair>, JsonError> {
+    let packets = input
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let value = serde_json::from_str::<Value>(line).map_err(JsonError::Syntax)?;
+            Packet::try_from(&value)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if packets.len() % 2 != 0 {
+        return Err(JsonError::Unpaired);
+    }
+    Ok(packets
+        .chunks_exact(2)
+        .map(|pair| Pair {
+            left: pair[0].clone(),
+            right: pair[1].clone(),
+        })
+        .collect())
+}
+
 fn compare(mut left: VecDeque<Packet>, mut right: VecDeque<Packet>) -> Ordering {
     let mut result = Ordering::Equal;
     while (left.len() > 0 || right.len() > 0) && result.is_eq() {
@@ -98,61 +160,91 @@ fn day13a(path: &str) -> usize {
         })
 }
 
-fn day13b(path: &str) -> usize {
-    let content = fs::read_to_string(path).expect("file not found");
-    let (_, mut packets) = parse(content.as_str()).expect("parsing failed");
-
-    let extra = Pair {
-        left: Packet::List(VecDeque::from([Packet::List(VecDeque::from([
-            Packet::Number(2),
-        ]))])),
-        right: Packet::List(VecDeque::from([Packet::List(VecDeque::from([
-            Packet::Number(6),
-        ]))])),
+fn compare_packets(left: &Packet, right: &Packet) -> Ordering {
+    let as_list = |packet: &Packet| match packet {
+        Packet::List(ls) => ls.clone(),
+        Packet::Number(n) => VecDeque::from([Packet::Number(*n)]),
     };
-    packets.push(extra);
+    compare(as_list(left), as_list(right))
+}
+
+#[derive(Debug)]
+struct Sorted {
+    packets: Vec<Packet>,
+    // 1-based position of each divider, in the order they were given
+    dividers: Vec<usize>,
+}
 
+fn sort_with_dividers(packets: Vec<Packet>, dividers: &[Packet]) -> Sorted {
     let mut all = packets
-        .iter()
-        .flat_map(|packet| {
-            let left = packet.left.clone();
-            let right = packet.right.clone();
-            return vec![left, right];
-        })
-        .map(|packet| match packet {
-            Packet::List(ls) => Some(ls),
-            Packet::Number(_) => None,
-        })
-        .flatten()
+        .into_iter()
+        .map(|packet| (packet, None))
+        .chain(
+            dividers
+                .iter()
+                .cloned()
+                .enumerate()
+                .map(|(i, d)| (d, Some(i))),
+        )
         .collect::<Vec<_>>();
+    all.sort_by(|(a, _), (b, _)| compare_packets(a, b));
 
-    all.sort_by(|a, b| {
-        let lls = a.clone();
-        let rrs = b.clone();
-        return compare(lls, rrs);
-    });
+    let mut positions = vec![0; dividers.len()];
+    for (idx, (_, divider)) in all.iter().enumerate() {
+        if let Some(i) = divider {
+            positions[*i] = idx + 1;
+        }
+    }
+    Sorted {
+        packets: all.into_iter().map(|(packet, _)| packet).collect(),
+        dividers: positions,
+    }
+}
 
-    all.into_iter()
+// same positions as sort_with_dividers, found by counting the packets that sort before each divider
+fn divider_positions<'a>(
+    packets: impl IntoIterator<Item = &'a Packet>,
+    dividers: &[Packet],
+) -> Vec<usize> {
+    let mut positions = dividers
+        .iter()
         .enumerate()
-        .filter_map(|(idx, item)| {
-            let two = match compare(
-                item.clone(),
-                VecDeque::from([Packet::List(VecDeque::from([Packet::Number(2)]))]),
-            ) {
-                Ordering::Less => None,
-                Ordering::Equal => Some(idx + 1),
-                Ordering::Greater => None,
-            };
-            let six = match compare(
-                item.clone(),
-                VecDeque::from([Packet::List(VecDeque::from([Packet::Number(6)]))]),
-            ) {
-                Ordering::Less => None,
-                Ordering::Equal => Some(idx + 1),
-                Ordering::Greater => None,
-            };
-            two.or(six)
+        .map(|(i, divider)| {
+            1 + dividers
+                .iter()
+                .enumerate()
+                .filter(|(j, other)| match compare_packets(other, divider) {
+                    Ordering::Less => true,
+                    Ordering::Equal => *j < i,
+                    Ordering::Greater => false,
+                })
+                .count()
         })
+        .collect::<Vec<_>>();
+
+    for packet in packets {
+        for (position, divider) in positions.iter_mut().zip(dividers) {
+            if compare_packets(packet, divider).is_le() {
+                *position += 1;
+            }
+        }
+    }
+    positions
+}
+
+fn day13b(path: &str) -> usize {
+    let content = fs::read_to_string(path).expect("file not found");
+    let (_, packets) = parse(content.as_str()).expect("parsing failed");
+    let (_, two) = packet("[[2]]").expect("parsing failed");
+    let (_, six) = packet("[[6]]").expect("parsing failed");
+
+    let packets = packets
+        .iter()
+        .flat_map(|pair| [&pair.left, &pair.right])
+        .collect::<Vec<_>>();
+
+    divider_positions(packets, &[two, six])
+        .into_iter()
         .product()
 }
 
@@ -183,4 +275,67 @@ mod tests {
         let actual = day13b("./data/day13final.txt");
         assert_eq!(actual, 25792);
     }
+
+    #[test]
+    fn sort_packets_with_custom_dividers() {
+        let content = fs::read_to_string("./data/day13.txt").expect("file not found");
+        let (_, pairs) = parse(content.as_str()).expect("parsing failed");
+        let packets = pairs
+            .into_iter()
+            .flat_map(|pair| [pair.left, pair.right])
+            .collect::<Vec<_>>();
+        let dividers = ["[[6]]", "[[2]]", "[]", "[10]"]
+            .iter()
+            .map(|input| packet(input).expect("parsing failed").1)
+            .collect::<Vec<_>>();
+
+        let expected = divider_positions(&packets, &dividers);
+        let actual = sort_with_dividers(packets, &dividers);
+        assert_eq!(actual.dividers, vec![15, 11, 2, 20]);
+        assert_eq!(actual.dividers, expected);
+        assert_eq!(actual.packets.len(), 20);
+        for (idx, divider) in actual.dividers.iter().zip(dividers.iter()) {
+            assert_eq!(&actual.packets[idx - 1], divider);
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let (_, expected) = packet("[1,[2,[3,[4,[5,6,7]]]],8,9]").expect("parsing failed");
+        let value = Value::from(&expected);
+        assert_eq!(value.to_string(), "[1,[2,[3,[4,[5,6,7]]]],8,9]");
+        let actual = Packet::try_from(&value).expect("conversion failed");
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn json_rejects_non_packets() {
+        let value: Value = serde_json::from_str("[1,[2.5]]").unwrap();
+        assert!(matches!(
+            Packet::try_from(&value),
+            Err(JsonError::NotAnInteger(_))
+        ));
+        let value: Value = serde_json::from_str("[1,-2]").unwrap();
+        assert!(matches!(
+            Packet::try_from(&value),
+            Err(JsonError::NotAnInteger(_))
+        ));
+        let value: Value = serde_json::from_str("[[\"a\"]]").unwrap();
+        assert!(matches!(
+            Packet::try_from(&value),
+            Err(JsonError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn json_parser_matches_nom_parser() {
+        let content = fs::read_to_string("./data/day13final.txt").expect("file not found");
+        let (_, expected) = parse(content.as_str()).expect("parsing failed");
+        let actual = parse_json(content.as_str()).expect("parsing failed");
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert_eq!(a.left, e.left);
+            assert_eq!(a.right, e.right);
+        }
+    }
 }
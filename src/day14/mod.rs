@@ -1,12 +1,21 @@
 #![allow(dead_code)]
 
-use std::{cmp, collections::HashMap, fmt::Display, fs};
+use std::{
+    cmp,
+    fmt::Display,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
 
 use nom::{
     bytes::complete::tag, character::complete::newline, multi::separated_list1,
     sequence::separated_pair, IResult,
 };
 
+const SOURCE: (u32, u32) = (500, 0);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Pixel {
     Rock,
     Air,
@@ -15,9 +24,14 @@ enum Pixel {
 
 impl Pixel {
     fn is_sand(&self) -> bool {
+        matches!(self, Pixel::Sand)
+    }
+
+    fn rgb(&self) -> [u8; 3] {
         match self {
-            Pixel::Sand => true,
-            _ => false,
+            Pixel::Rock => [110, 110, 110],
+            Pixel::Air => [20, 20, 30],
+            Pixel::Sand => [230, 190, 90],
         }
     }
 }
@@ -32,6 +46,21 @@ impl Display for Pixel {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+enum FrameFormat {
+    Text,
+    Ppm,
+}
+
+impl FrameFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            FrameFormat::Text => "txt",
+            FrameFormat::Ppm => "ppm",
+        }
+    }
+}
+
 fn parse(input: &str) -> IResult<&str, Vec<Vec<(u32, u32)>>> {
     separated_list1(
         newline,
@@ -46,154 +75,288 @@ fn parse(input: &str) -> IResult<&str, Vec<Vec<(u32, u32)>>> {
     )(input)
 }
 
-fn print(map: &HashMap<(u32, u32), Pixel>) {
-    for y in 0..12 {
-        for x in 490..510 {
-            match map.get(&(x, y)) {
-                Some(pixel) => print!("{}", pixel),
-                None => print!("{}", Pixel::Air),
-            }
+// expand the rock paths into every coordinate they cover
+fn rocks(paths: &[Vec<(u32, u32)>]) -> Vec<(u32, u32)> {
+    paths
+        .iter()
+        .flat_map(|path| {
+            path.iter()
+                .zip(path.iter().skip(1))
+                .flat_map(|(&(x, y), &(xx, yy))| {
+                    (cmp::min(y, yy)..=cmp::max(y, yy))
+                        .flat_map(move |y| (cmp::min(x, xx)..=cmp::max(x, xx)).map(move |x| (x, y)))
+                })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Floor {
+    // sand falling below the lowest rock is lost
+    Abyss,
+    // a solid floor this many rows below the lowest rock
+    Offset(u32),
+    // a solid floor at this exact depth
+    Depth(u32),
+}
+
+struct Cave {
+    rocks: Vec<(u32, u32)>,
+    sources: Vec<(u32, u32)>,
+    floor: Floor,
+}
+
+impl Cave {
+    fn new(rocks: Vec<(u32, u32)>) -> Self {
+        Cave {
+            rocks,
+            sources: vec![],
+            floor: Floor::Abyss,
         }
-        println!()
     }
-}
 
-fn drip(
-    coord: (u32, u32),
-    map: &mut HashMap<(u32, u32), Pixel>,
-    max_depth: u32,
-    partb: bool,
-) -> bool {
-    let coord = dripping(coord, map, max_depth, partb);
-    if coord == Some((500, 0)) {
-        return false;
+    fn source(mut self, source: (u32, u32)) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    fn floor(mut self, floor: Floor) -> Self {
+        self.floor = floor;
+        self
     }
-    match coord {
-        Some((x, y)) => {
-            map.insert((x, y), Pixel::Sand);
-            true
+
+    fn build(&self) -> Grid {
+        let sources = match self.sources.is_empty() {
+            true => vec![SOURCE],
+            false => self.sources.clone(),
+        };
+        let y_max = self.rocks.iter().map(|&(_, y)| y).max().unwrap_or(0);
+        let height = match self.floor {
+            Floor::Abyss => y_max + 1,
+            Floor::Offset(offset) => y_max + offset,
+            Floor::Depth(depth) => depth,
+        };
+        // sand moves at most one column per row, so it can never leave this window
+        let x_min = self
+            .rocks
+            .iter()
+            .map(|&(x, _)| x)
+            .chain(sources.iter().map(|&(x, _)| x.saturating_sub(height)))
+            .min()
+            .unwrap();
+        let x_max = self
+            .rocks
+            .iter()
+            .map(|&(x, _)| x)
+            .chain(sources.iter().map(|&(x, _)| x + height))
+            .max()
+            .unwrap();
+
+        let width = (x_max - x_min + 1) as usize;
+        let height = height as usize;
+        let mut grid = Grid {
+            cells: vec![Pixel::Air; width * height],
+            x_min,
+            width,
+            height,
+            floor: self.floor,
+            sources: sources
+                .iter()
+                .map(|&(x, y)| ((x - x_min) as usize, y as usize))
+                .collect(),
+            paths: vec![vec![]; sources.len()],
+        };
+        for &(x, y) in &self.rocks {
+            if (y as usize) < height {
+                let idx = grid.index(((x - x_min) as usize, y as usize));
+                grid.cells[idx] = Pixel::Rock;
+            }
         }
-        None => false,
+        grid
     }
 }
 
-fn is_out_of_bounds((_, y): (u32, u32), max_depth: u32) -> bool {
-    y > max_depth
+struct Grid {
+    cells: Vec<Pixel>,
+    x_min: u32,
+    width: usize,
+    height: usize,
+    // with a solid floor it sits just below the last row, otherwise sand falls out of the grid
+    floor: Floor,
+    sources: Vec<(usize, usize)>,
+    // trail of the last grain per source, the next one follows it up to where it came to rest
+    paths: Vec<Vec<(usize, usize)>>,
 }
 
-fn dripping(
-    (x, y): (u32, u32),
-    map: &mut HashMap<(u32, u32), Pixel>,
-    max_depth: u32,
-    partb: bool,
-) -> Option<(u32, u32)> {
-    if is_out_of_bounds((x, y), max_depth) {
-        return None;
+impl Grid {
+    fn index(&self, (x, y): (usize, usize)) -> usize {
+        y * self.width + x
     }
 
-    let below = map.get(&(x, y + 1)).or_else(|| match partb {
-        true => {
-            if y + 1 == max_depth {
-                Some(&Pixel::Rock)
-            } else {
-                None
-            }
+    fn get(&self, (x, y): (u32, u32)) -> Pixel {
+        if x < self.x_min || (x - self.x_min) as usize >= self.width || y as usize >= self.height {
+            return Pixel::Air;
         }
-        false => None,
-    });
-    let left = map.get(&(x - 1, y + 1)).or_else(|| match partb {
-        true => {
-            if y + 1 == max_depth {
-                Some(&Pixel::Rock)
-            } else {
-                None
+        self.cells[self.index(((x - self.x_min) as usize, y as usize))]
+    }
+
+    // drop a single grain from the given source, returning where it came to rest
+    fn drop_grain(&mut self, source: usize) -> Option<(u32, u32)> {
+        let mut path = std::mem::take(&mut self.paths[source]);
+        // grains from other sources may have settled on the trail since it was recorded
+        while path
+            .last()
+            .is_some_and(|&cell| self.cells[self.index(cell)] != Pixel::Air)
+        {
+            path.pop();
+        }
+        if path.is_empty() {
+            let start = self.sources[source];
+            if start.0 >= self.width
+                || start.1 >= self.height
+                || self.cells[self.index(start)] != Pixel::Air
+            {
+                return None;
             }
+            path.push(start);
         }
-        false => None,
-    });
-    let right = map.get(&(x + 1, y + 1)).or_else(|| match partb {
-        true => {
-            if y + 1 == max_depth {
-                Some(&Pixel::Rock)
+
+        let mut rested = None;
+        while let Some(&(x, y)) = path.last() {
+            if y + 1 == self.height {
+                if self.floor == Floor::Abyss {
+                    break;
+                }
             } else {
-                None
+                let next = [x, x.wrapping_sub(1), x + 1]
+                    .into_iter()
+                    .filter(|&x| x < self.width)
+                    .map(|x| (x, y + 1))
+                    .find(|&next| self.cells[self.index(next)] == Pixel::Air);
+                if let Some(next) = next {
+                    path.push(next);
+                    continue;
+                }
             }
-        }
-        false => None,
-    });
 
-    match (left, below, right) {
-        (None, None, None) => dripping((x, y + 1), map, max_depth, partb),
-        (None, None, Some(_)) => dripping((x, y + 1), map, max_depth, partb),
-        (None, Some(_), None) => dripping((x - 1, y + 1), map, max_depth, partb),
-        (Some(_), None, None) => dripping((x, y + 1), map, max_depth, partb),
-        (None, Some(_), Some(_)) => dripping((x - 1, y + 1), map, max_depth, partb),
-        (Some(_), None, Some(_)) => dripping((x, y + 1), map, max_depth, partb),
-        (Some(_), Some(_), None) => dripping((x + 1, y + 1), map, max_depth, partb),
-        (Some(_), Some(_), Some(_)) => Some((x, y)),
+            path.pop();
+            let idx = self.index((x, y));
+            self.cells[idx] = Pixel::Sand;
+            rested = Some((x as u32 + self.x_min, y as u32));
+            break;
+        }
+        self.paths[source] = path;
+        rested
     }
-}
 
-fn day14a(path: &str) -> usize {
-    let content = fs::read_to_string(path).expect("file not found");
-    let (_, coords) = parse(content.as_str()).expect("parsing failed");
+    // drop grains from every source in turn, a source stops once a grain falls out or it is
+    // blocked, returns the amount of rested grains per source
+    fn fill(&mut self) -> Vec<usize> {
+        self.fill_recording(0, |_, _| {})
+    }
 
-    let mut map = coords
-        .iter()
-        .map(|coord| {
-            coord.iter().zip(coord.iter().skip(1)).fold(
-                HashMap::new(),
-                |mut acc, ((x, y), (xx, yy))| {
-                    for y in cmp::min(*y, *yy)..=cmp::max(*y, *yy) {
-                        for x in cmp::min(*x, *xx)..=cmp::max(*x, *xx) {
-                            acc.insert((x, y), Pixel::Rock);
+    // like `fill`, but hands the grid to `record` after every `every` grains and once at the end
+    fn fill_recording(&mut self, every: usize, mut record: impl FnMut(usize, &Grid)) -> Vec<usize> {
+        let mut counts = vec![0; self.sources.len()];
+        let mut active = vec![true; self.sources.len()];
+        let mut total = 0;
+        while active.iter().any(|&active| active) {
+            for source in 0..self.sources.len() {
+                if !active[source] {
+                    continue;
+                }
+                match self.drop_grain(source) {
+                    Some(_) => {
+                        counts[source] += 1;
+                        total += 1;
+                        if every > 0 && total % every == 0 {
+                            record(total, self);
                         }
                     }
-                    acc
-                },
-            )
-        })
-        .flatten()
-        .collect::<HashMap<_, _>>();
+                    None => active[source] = false,
+                }
+            }
+        }
+        if every == 0 || total % every != 0 {
+            record(total, self);
+        }
+        counts
+    }
 
-    let max_y = *map.keys().map(|(_, y)| y).max().unwrap();
-    while drip((500, 0), &mut map, max_y, false) {}
+    fn sand(&self) -> usize {
+        self.cells.iter().filter(|pixel| pixel.is_sand()).count()
+    }
 
-    map.values().filter(|pixel| pixel.is_sand()).count()
+    fn frame(&self, format: FrameFormat) -> Vec<u8> {
+        match format {
+            FrameFormat::Text => self.to_string().into_bytes(),
+            FrameFormat::Ppm => {
+                let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+                ppm.extend(self.cells.iter().flat_map(|pixel| pixel.rgb()));
+                ppm
+            }
+        }
+    }
 }
 
-fn day14b(path: &str) -> usize {
+impl Display for Grid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in self.cells.chunks(self.width) {
+            for pixel in row {
+                write!(f, "{}", pixel)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// write a numbered frame into `dir` every `every` grains, returns the amount of frames written
+fn write_frames(
+    grid: &mut Grid,
+    dir: &Path,
+    every: usize,
+    format: FrameFormat,
+) -> io::Result<usize> {
+    let mut frames = 0;
+    let mut result = Ok(());
+    grid.fill_recording(every, |_, grid| {
+        if result.is_err() {
+            return;
+        }
+        let name = dir.join(format!("frame_{:05}.{}", frames, format.extension()));
+        result = fs::File::create(name).and_then(|mut file| file.write_all(&grid.frame(format)));
+        frames += 1;
+    });
+    result.map(|_| frames)
+}
+
+fn day14a(path: &str) -> usize {
     let content = fs::read_to_string(path).expect("file not found");
-    let (_, coords) = parse(content.as_str()).expect("parsing failed");
+    let (_, paths) = parse(content.as_str()).expect("parsing failed");
 
-    let mut map = coords
-        .iter()
-        .map(|coord| {
-            coord.iter().zip(coord.iter().skip(1)).fold(
-                HashMap::new(),
-                |mut acc, ((x, y), (xx, yy))| {
-                    for y in cmp::min(*y, *yy)..=cmp::max(*y, *yy) {
-                        for x in cmp::min(*x, *xx)..=cmp::max(*x, *xx) {
-                            acc.insert((x, y), Pixel::Rock);
-                        }
-                    }
-                    acc
-                },
-            )
-        })
-        .flatten()
-        .collect::<HashMap<_, _>>();
+    let mut grid = Cave::new(rocks(&paths)).build();
+    grid.fill().into_iter().sum()
+}
 
-    let y_max = *map.keys().map(|(_, y)| y).max().unwrap();
-    while drip((500, 0), &mut map, y_max + 2, true) {}
+fn day14b(path: &str) -> usize {
+    let content = fs::read_to_string(path).expect("file not found");
+    let (_, paths) = parse(content.as_str()).expect("parsing failed");
 
-    map.values().filter(|pixel| pixel.is_sand()).count() + 1
+    let mut grid = Cave::new(rocks(&paths)).floor(Floor::Offset(2)).build();
+    grid.fill().into_iter().sum()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn example() -> Vec<(u32, u32)> {
+        let content = fs::read_to_string("./data/day14.txt").expect("file not found");
+        let (_, paths) = parse(content.as_str()).expect("parsing failed");
+        rocks(&paths)
+    }
+
     #[test]
     fn find_amount_of_rested_sand() {
         let actual = day14a("./data/day14.txt");
@@ -217,4 +380,86 @@ mod tests {
         let actual = day14b("./data/day14final.txt");
         assert_eq!(actual, 29076);
     }
+
+    #[test]
+    fn floor_at_explicit_depth() {
+        let mut grid = Cave::new(example()).floor(Floor::Depth(11)).build();
+        assert_eq!(grid.fill(), vec![93]);
+
+        // a floor right below the source only leaves room for a single row of sand
+        let mut grid = Cave::new(vec![]).floor(Floor::Depth(1)).build();
+        assert_eq!(grid.fill(), vec![1]);
+    }
+
+    #[test]
+    fn count_sand_per_source() {
+        // sources far enough apart never interact
+        let mut grid = Cave::new(vec![])
+            .source((500, 0))
+            .source((600, 0))
+            .floor(Floor::Depth(3))
+            .build();
+        assert_eq!(grid.fill(), vec![9, 9]);
+
+        // the second source is buried by the first one's pile
+        let mut grid = Cave::new(example())
+            .source((500, 0))
+            .source((500, 5))
+            .floor(Floor::Offset(2))
+            .build();
+        let counts = grid.fill();
+        assert_eq!(counts.iter().sum::<usize>(), 93);
+        assert_eq!(grid.sand(), 93);
+        assert!(counts[1] > 0);
+
+        let mut grid = Cave::new(example())
+            .source((497, 0))
+            .source((503, 0))
+            .build();
+        let counts = grid.fill();
+        assert_eq!(grid.sand(), counts.iter().sum::<usize>());
+        assert_eq!(grid.get((497, 0)), Pixel::Air);
+    }
+
+    #[test]
+    fn render_text_frames() {
+        let mut grid = Cave::new(example()).build();
+
+        let mut frames = vec![];
+        let counts = grid.fill_recording(5, |count, grid| {
+            frames.push((
+                count,
+                String::from_utf8(grid.frame(FrameFormat::Text)).unwrap(),
+            ))
+        });
+
+        assert_eq!(counts, vec![24]);
+        assert_eq!(grid.sand(), 24);
+        assert_eq!(
+            frames.iter().map(|(count, _)| *count).collect::<Vec<_>>(),
+            vec![5, 10, 15, 20, 24]
+        );
+        let last = frames.last().unwrap().1.lines().collect::<Vec<_>>();
+        assert_eq!(last.len(), 10);
+        assert_eq!(grid.get((500, 2)), Pixel::Sand);
+        assert_eq!(grid.get((498, 4)), Pixel::Rock);
+        assert!(last[9].contains("#########"));
+    }
+
+    #[test]
+    fn write_ppm_frames() {
+        let mut grid = Cave::new(example()).floor(Floor::Offset(2)).build();
+
+        let dir = std::env::temp_dir().join(format!("day14_frames_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let frames = write_frames(&mut grid, &dir, 10, FrameFormat::Ppm).unwrap();
+        let last = fs::read(dir.join("frame_00009.ppm")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(frames, 10);
+        assert_eq!(grid.sand(), 93);
+        let header = format!("P6\n{} {}\n255\n", grid.width, grid.height);
+        assert!(last.starts_with(header.as_bytes()));
+        assert_eq!(last.len(), header.len() + grid.width * grid.height * 3);
+    }
 }
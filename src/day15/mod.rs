@@ -5,6 +5,7 @@ use std::{
     collections::HashSet,
     fmt::{Display, Formatter},
     fs,
+    ops::RangeInclusive,
 };
 
 use nom::{
@@ -27,11 +28,83 @@ impl From<(i32, i32)> for Coord {
     }
 }
 
+// how the distance between a sensor and a cell is measured
+trait Metric {
+    fn distance(&self, from: (i32, i32), to: (i32, i32)) -> i32;
+
+    // half the width of the span covered `dy` rows away from the sensor, `None` when the row
+    // is out of reach
+    fn reach(&self, distance: i32, dy: i32) -> Option<i32>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Manhattan;
+
+impl Metric for Manhattan {
+    fn distance(&self, (x, y): (i32, i32), (xx, yy): (i32, i32)) -> i32 {
+        (x - xx).abs() + (y - yy).abs()
+    }
+
+    fn reach(&self, distance: i32, dy: i32) -> Option<i32> {
+        let reach = distance - dy.abs();
+        (reach >= 0).then_some(reach)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Chebyshev;
+
+impl Metric for Chebyshev {
+    fn distance(&self, (x, y): (i32, i32), (xx, yy): (i32, i32)) -> i32 {
+        (x - xx).abs().max((y - yy).abs())
+    }
+
+    fn reach(&self, distance: i32, dy: i32) -> Option<i32> {
+        (dy.abs() <= distance).then_some(distance)
+    }
+}
+
+// straight line distance rounded to the nearest integer
+#[derive(Debug, Clone, Copy, Default)]
+struct Euclidean;
+
+impl Metric for Euclidean {
+    fn distance(&self, (x, y): (i32, i32), (xx, yy): (i32, i32)) -> i32 {
+        let (dx, dy) = ((x - xx) as i64, (y - yy) as i64);
+        let squared = dx * dx + dy * dy;
+        let root = isqrt(squared);
+        // sqrt(squared) rounds up once it passes root + 0.5
+        if squared > root * root + root {
+            (root + 1) as i32
+        } else {
+            root as i32
+        }
+    }
+
+    fn reach(&self, distance: i32, dy: i32) -> Option<i32> {
+        let (distance, dy) = (distance as i64, dy as i64);
+        let left = distance * distance + distance - dy * dy;
+        (left >= 0).then(|| isqrt(left) as i32)
+    }
+}
+
+fn isqrt(n: i64) -> i64 {
+    let mut root = (n as f64).sqrt() as i64;
+    while root * root > n {
+        root -= 1;
+    }
+    while (root + 1) * (root + 1) <= n {
+        root += 1;
+    }
+    root
+}
+
 #[derive(Debug)]
-struct Reading {
+struct Reading<M: Metric = Manhattan> {
     sensor: Coord,
     beacon: Coord,
     distance: i32,
+    metric: M,
 }
 
 impl Display for Coord {
@@ -42,49 +115,67 @@ impl Display for Coord {
 
 impl Reading {
     fn new(sensor: Coord, beacon: Coord) -> Reading {
-        let distance = (sensor.x - beacon.x).abs() + (sensor.y - beacon.y).abs();
+        Reading::with_metric(sensor, beacon, Manhattan)
+    }
+}
+
+impl<M: Metric> Reading<M> {
+    fn with_metric(sensor: Coord, beacon: Coord, metric: M) -> Reading<M> {
+        let distance = metric.distance((sensor.x, sensor.y), (beacon.x, beacon.y));
         Reading {
             sensor,
             beacon,
             distance,
+            metric,
         }
     }
 
+    // the same sensor and beacon, measured with another metric
+    fn measured_with<N: Metric>(self, metric: N) -> Reading<N> {
+        Reading::with_metric(self.sensor, self.beacon, metric)
+    }
+
     fn within_range(&self, y: i32) -> bool {
-        self.sensor.y - self.distance as i32 <= y && y <= self.sensor.y + self.distance as i32
+        self.metric
+            .reach(self.distance, self.sensor.y - y)
+            .is_some()
     }
 
-    fn calculate_distance_to(&self, (x, y): (i32, i32)) -> i32 {
-        (self.sensor.x - x).abs() + (self.sensor.y - y).abs()
+    fn calculate_distance_to(&self, coord: (i32, i32)) -> i32 {
+        self.metric.distance((self.sensor.x, self.sensor.y), coord)
     }
 
-    fn border_coords(&self, offset: i32) -> Vec<(i32, i32)> {
-        let mut coords = Vec::new();
-        let mut x = 0;
-        for y in self.sensor.y - self.distance - offset..=self.sensor.y + self.distance + offset {
-            coords.push((self.sensor.x + x, y));
-            coords.push((self.sensor.x - x, y));
-            if y >= self.sensor.y {
-                x -= 1;
-            } else {
-                x += 1;
-            }
-        }
-        coords
+    fn covers(&self, coord: (i32, i32)) -> bool {
+        self.calculate_distance_to(coord) <= self.distance
+    }
+
+    // whether any cell of the rectangle is within range
+    fn touches(&self, (x0, y0): (i32, i32), (x1, y1): (i32, i32)) -> bool {
+        self.covers((self.sensor.x.clamp(x0, x1), self.sensor.y.clamp(y0, y1)))
+    }
+
+    // whether every cell of the rectangle is within range, the covered area is convex for all
+    // metrics so checking the corners is enough
+    fn contains(&self, (x0, y0): (i32, i32), (x1, y1): (i32, i32)) -> bool {
+        [(x0, y0), (x0, y1), (x1, y0), (x1, y1)]
+            .into_iter()
+            .all(|corner| self.covers(corner))
     }
 
-    fn coords_on_y_axis(&self, y: i32) -> Vec<(i32, i32)> {
-        let min = self.sensor.x - self.distance;
-        let max = self.sensor.x + self.distance;
-        (min..=max)
-            .map(|x| (x, y))
-            .filter(|&coord| Coord::from(coord) != self.beacon)
-            .filter(|&coord| self.calculate_distance_to(coord) <= self.distance)
-            .collect()
+    // the x positions this sensor covers on row `y`
+    fn range_on_row(&self, y: i32) -> Option<RangeInclusive<i32>> {
+        let reach = self.metric.reach(self.distance, self.sensor.y - y)?;
+        Some(self.sensor.x - reach..=self.sensor.x + reach)
+    }
+
+    // the y positions this sensor covers on column `x`
+    fn range_on_column(&self, x: i32) -> Option<RangeInclusive<i32>> {
+        let reach = self.metric.reach(self.distance, self.sensor.x - x)?;
+        Some(self.sensor.y - reach..=self.sensor.y + reach)
     }
 }
 
-impl Display for Reading {
+impl<M: Metric> Display for Reading<M> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(
             f,
@@ -117,69 +208,124 @@ fn parse(input: &str) -> IResult<&str, Vec<Reading>> {
     separated_list1(newline, parse_reading)(input).map(|(input, readings)| (input, readings))
 }
 
-fn day15a(path: &str, y: i32) -> usize {
-    let content = fs::read_to_string(path).expect("file not found");
-    let (_, readings) = parse(content.as_str()).expect("parsing failed");
-    readings
-        .iter()
-        .filter(|reading| reading.within_range(y))
-        .flat_map(|reading| reading.coords_on_y_axis(y))
-        .collect::<HashSet<(i32, i32)>>()
-        .iter()
-        .count()
-}
-
-fn print(candidates: &HashSet<(i32, i32)>) {
-    for y in 10..20 {
-        let mut line = String::new();
-        for x in -10..35 {
-            if candidates.contains(&(x, y)) {
-                line.push('#');
-            } else if x == 14 && y == 11 {
-                line.push('o');
-            } else {
-                line.push(' ');
+// merge overlapping or adjacent ranges into a sorted list of disjoint ranges
+fn merge(mut ranges: Vec<RangeInclusive<i32>>) -> Vec<RangeInclusive<i32>> {
+    ranges.sort_by_key(|range| *range.start());
+    let mut merged: Vec<RangeInclusive<i32>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if *range.start() <= last.end() + 1 => {
+                if range.end() > last.end() {
+                    *last = *last.start()..=*range.end();
+                }
             }
+            _ => merged.push(range),
         }
-        println!("{}", line);
     }
+    merged
 }
 
-fn day15b(path: &str, max: i32) -> usize {
+fn coverage_on_row<M: Metric>(readings: &[Reading<M>], y: i32) -> Vec<RangeInclusive<i32>> {
+    merge(
+        readings
+            .iter()
+            .filter_map(|reading| reading.range_on_row(y))
+            .collect(),
+    )
+}
+
+fn coverage_on_column<M: Metric>(readings: &[Reading<M>], x: i32) -> Vec<RangeInclusive<i32>> {
+    merge(
+        readings
+            .iter()
+            .filter_map(|reading| reading.range_on_column(x))
+            .collect(),
+    )
+}
+
+// every cell within `x_range` x `y_range` that no sensor covers
+fn uncovered_cells<M: Metric>(
+    readings: &[Reading<M>],
+    x_range: RangeInclusive<i32>,
+    y_range: RangeInclusive<i32>,
+) -> Vec<(i32, i32)> {
+    let mut cells = vec![];
+    let readings = readings.iter().collect::<Vec<_>>();
+    subdivide(
+        &readings,
+        (*x_range.start(), *y_range.start()),
+        (*x_range.end(), *y_range.end()),
+        &mut cells,
+    );
+    cells.sort_by_key(|&(x, y)| (y, x));
+    cells
+}
+
+// quadtree search, splitting the rectangle until it is either inside a single sensor's range
+// or out of reach of all of them
+fn subdivide<M: Metric>(
+    readings: &[&Reading<M>],
+    (x0, y0): (i32, i32),
+    (x1, y1): (i32, i32),
+    cells: &mut Vec<(i32, i32)>,
+) {
+    if x0 > x1 || y0 > y1 {
+        return;
+    }
+    let touching = readings
+        .iter()
+        .copied()
+        .filter(|reading| reading.touches((x0, y0), (x1, y1)))
+        .collect::<Vec<_>>();
+    if touching.is_empty() {
+        cells.extend((y0..=y1).flat_map(|y| (x0..=x1).map(move |x| (x, y))));
+        return;
+    }
+    if touching
+        .iter()
+        .any(|reading| reading.contains((x0, y0), (x1, y1)))
+    {
+        return;
+    }
+
+    let xm = x0 + (x1 - x0) / 2;
+    let ym = y0 + (y1 - y0) / 2;
+    subdivide(&touching, (x0, y0), (xm, ym), cells);
+    subdivide(&touching, (xm + 1, y0), (x1, ym), cells);
+    subdivide(&touching, (x0, ym + 1), (xm, y1), cells);
+    subdivide(&touching, (xm + 1, ym + 1), (x1, y1), cells);
+}
+
+fn tuning_frequency((x, y): (i32, i32)) -> usize {
+    x as usize * 4000000 + y as usize
+}
+
+fn day15a(path: &str, y: i32) -> usize {
     let content = fs::read_to_string(path).expect("file not found");
     let (_, readings) = parse(content.as_str()).expect("parsing failed");
 
-    let readings: Vec<_> = readings
+    let coverage = coverage_on_row(&readings, y);
+    let covered: usize = coverage
         .iter()
-        .filter(|reading| reading.beacon.x <= max && reading.beacon.y <= max)
-        .collect();
-    let candidates: HashSet<_> = readings
+        .map(|range| (range.end() - range.start() + 1) as usize)
+        .sum();
+    // cells holding a known beacon can obviously contain a beacon
+    let beacons = readings
         .iter()
-        .flat_map(|reading| reading.border_coords(1))
-        .filter(|(x, y)| {
-            // filter on coords that are not in range of any reading
-            !readings
-                .iter()
-                .any(|r| r.calculate_distance_to((*x, *y)) <= r.distance)
-        })
-        .map(|(x, y)| {
-            // count how many readings are in range of this coord
-            let borders = readings.iter().fold(0, |acc, r| {
-                if r.calculate_distance_to((x, y)) == r.distance + 1 {
-                    acc + 1
-                } else {
-                    acc
-                }
-            });
-            (x, y, borders)
-        })
-        .collect();
+        .filter(|reading| reading.beacon.y == y)
+        .map(|reading| reading.beacon.x)
+        .filter(|x| coverage.iter().any(|range| range.contains(x)))
+        .collect::<HashSet<_>>()
+        .len();
+    covered - beacons
+}
 
-    candidates
-        .iter()
-        .max_by(|(_, _, first), (_, _, other)| first.cmp(other))
-        .and_then(|(x, y, _)| Some(*x as usize * 4000000 + *y as usize))
-        .unwrap() as usize
+fn day15b(path: &str, max: i32) -> usize {
+    let content = fs::read_to_string(path).expect("file not found");
+    let (_, readings) = parse(content.as_str()).expect("parsing failed");
+
+    let cells = uncovered_cells(&readings, 0..=max, 0..=max);
+    tuning_frequency(cells[0])
 }
 
 #[cfg(test)]
@@ -192,6 +338,79 @@ mod tests {
         assert_eq!(actual, 26);
     }
 
+    #[test]
+    fn merge_coverage_on_row() {
+        let content = fs::read_to_string("./data/day15.txt").expect("file not found");
+        let (_, readings) = parse(content.as_str()).expect("parsing failed");
+        assert_eq!(coverage_on_row(&readings, 10), vec![-2..=24]);
+        assert_eq!(coverage_on_row(&readings, 11), vec![-3..=13, 15..=25]);
+        assert_eq!(coverage_on_column(&readings, 14), vec![0..=10, 12..=23]);
+        assert_eq!(coverage_on_row(&readings, 1000), vec![]);
+    }
+
+    #[test]
+    fn find_all_uncovered_cells() {
+        let content = fs::read_to_string("./data/day15.txt").expect("file not found");
+        let (_, readings) = parse(content.as_str()).expect("parsing failed");
+        assert_eq!(uncovered_cells(&readings, 0..=20, 0..=20), vec![(14, 11)]);
+        assert_eq!(uncovered_cells(&readings, 14..=14, 11..=11), vec![(14, 11)]);
+        assert_eq!(uncovered_cells(&readings, 0..=10, 0..=10), vec![]);
+
+        let expected = (-10..=35)
+            .flat_map(|y| (-15..=40).map(move |x| (x, y)))
+            .filter(|&coord| !readings.iter().any(|reading| reading.covers(coord)))
+            .collect::<Vec<_>>();
+        assert_eq!(uncovered_cells(&readings, -15..=40, -10..=35), expected);
+    }
+
+    fn assert_matches_brute_force<M: Metric>(readings: &[Reading<M>]) {
+        let covered = |coord| readings.iter().any(|reading| reading.covers(coord));
+        let expected = (-10..=35)
+            .flat_map(|y| (-15..=40).map(move |x| (x, y)))
+            .filter(|&coord| !covered(coord))
+            .collect::<Vec<_>>();
+        assert_eq!(uncovered_cells(readings, -15..=40, -10..=35), expected);
+
+        for y in -10..=35 {
+            let actual = coverage_on_row(readings, y)
+                .into_iter()
+                .flat_map(|range| range.filter(|x| (-15..=40).contains(x)))
+                .collect::<Vec<_>>();
+            let expected = (-15..=40).filter(|&x| covered((x, y))).collect::<Vec<_>>();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn find_uncovered_cells_with_other_metrics() {
+        let content = fs::read_to_string("./data/day15.txt").expect("file not found");
+        let (_, readings) = parse(content.as_str()).expect("parsing failed");
+        assert_matches_brute_force(&readings);
+
+        let chebyshev = readings
+            .into_iter()
+            .map(|reading| reading.measured_with(Chebyshev))
+            .collect::<Vec<_>>();
+        assert_matches_brute_force(&chebyshev);
+        assert_eq!(coverage_on_row(&chebyshev, 10), vec![-8..=25]);
+
+        let euclidean = chebyshev
+            .into_iter()
+            .map(|reading| reading.measured_with(Euclidean))
+            .collect::<Vec<_>>();
+        assert_matches_brute_force(&euclidean);
+    }
+
+    #[test]
+    fn round_euclidean_distance() {
+        assert_eq!(Euclidean.distance((0, 0), (3, 4)), 5);
+        assert_eq!(Euclidean.distance((0, 0), (1, 1)), 1);
+        assert_eq!(Euclidean.distance((0, 0), (2, 2)), 3);
+        assert_eq!(Euclidean.reach(5, 0), Some(5));
+        assert_eq!(Euclidean.reach(5, 5), Some(2));
+        assert_eq!(Euclidean.reach(5, 6), None);
+    }
+
     #[test]
     fn find_beacon() {
         let actual = day15b("./data/day15.txt", 20);
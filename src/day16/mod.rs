@@ -1,12 +1,6 @@
 #![allow(dead_code)]
 
-use std::{
-    cmp,
-    collections::{HashMap, HashSet},
-    fs,
-};
-
-use itertools::iproduct;
+use std::{collections::HashMap, fmt::Write, fs};
 
 use nom::{
     branch::alt,
@@ -17,150 +11,6 @@ use nom::{
     IResult,
 };
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct ElephantState {
-    name: String,
-    minute: u32,
-    pub elephant: String,
-    elephant_minute: u32,
-    score: u32,
-    visited: HashSet<String>,
-}
-
-impl ElephantState {
-    fn closed(&self, valve: &str) -> bool {
-        !self.visited.contains(valve)
-    }
-
-    fn score(&mut self, until: u32, minute: u32, scores: &HashMap<&str, u32>) {
-        // score self
-        if minute == self.minute && self.closed(self.name.as_str()) {
-            self.score += (until - minute) * scores[&self.name.as_str()];
-            self.visited.insert(self.name.clone());
-            self.minute += 1;
-        }
-
-        // score elephant
-        if minute == self.elephant_minute && self.closed(&self.elephant.as_str()) {
-            self.score += (until - minute) * scores[&self.elephant.as_str()];
-            self.visited.insert(self.elephant.clone());
-            self.elephant_minute += 1;
-        }
-    }
-
-    fn try_move(
-        &self,
-        minute: u32,
-        can_be_opened: &HashSet<String>,
-        weights: &HashMap<(String, String), (u32, u32)>,
-    ) -> Vec<Option<ElephantState>> {
-        if minute != self.minute && minute != self.elephant_minute {
-            return vec![None];
-        }
-
-        if self.visited.is_superset(can_be_opened) {
-            return vec![None];
-        }
-
-        let mut result = vec![];
-
-        match (minute == self.minute, minute == self.elephant_minute) {
-            (true, true) => {
-                let candidates = can_be_opened
-                    .difference(&self.visited)
-                    .collect::<Vec<&String>>();
-
-                if candidates.len() == 1 {
-                    let (first, _) = weights[&(self.name.clone(), candidates[0].clone())];
-                    let (second, _) = weights[&(self.elephant.clone(), candidates[0].clone())];
-
-                    match first <= second {
-                        true => {
-                            let mut new_state = self.clone();
-                            new_state.name = candidates[0].clone();
-                            new_state.minute += first;
-                            result.push(Some(new_state));
-                        }
-                        false => {
-                            let mut new_state = self.clone();
-                            new_state.elephant = candidates[0].clone();
-                            new_state.elephant_minute += second;
-                            result.push(Some(new_state));
-                        }
-                    }
-                    return result;
-                }
-
-                let combis = iproduct!(candidates.clone(), candidates.clone())
-                    .filter(|&(a, b)| a != b)
-                    .collect::<Vec<(&String, &String)>>();
-
-                for (a, b) in combis {
-                    let mut new_state = self.clone();
-                    let (first, _) = weights[&(self.name.clone(), a.clone())];
-                    let (second, _) = weights[&(self.elephant.clone(), b.clone())];
-                    new_state.name = a.clone();
-                    new_state.elephant = b.clone();
-                    new_state.minute += first;
-                    new_state.elephant_minute += second;
-                    result.push(Some(new_state));
-                }
-
-                result
-            }
-            (true, false) => {
-                for valve in can_be_opened.iter() {
-                    if self.closed(valve.as_str()) {
-                        let mut new_state = self.clone();
-                        let (moves, _) = weights[&(self.name.clone(), valve.clone())];
-                        new_state.name = valve.clone();
-                        new_state.minute += moves;
-                        result.push(Some(new_state));
-                    }
-                }
-                result
-            }
-            (false, true) => {
-                for valve in can_be_opened.iter() {
-                    if self.closed(valve.as_str()) {
-                        let mut new_state = self.clone();
-                        let (moves, _) = weights[&(self.elephant.clone(), valve.clone())];
-                        new_state.elephant = valve.clone();
-                        new_state.elephant_minute += moves;
-                        result.push(Some(new_state));
-                    }
-                }
-                result
-            }
-            (false, false) => panic!("invalid state due to previous check"),
-        }
-    }
-}
-
-#[derive(Debug, Clone, PartialEq)]
-struct State {
-    name: String,
-    score: u32,
-    minute: u32,
-    visited: HashSet<String>,
-}
-
-impl State {
-    fn new(name: String, minute: u32) -> Self {
-        Self {
-            name,
-            score: 0,
-            minute,
-            visited: HashSet::from(["AA".to_string()]),
-        }
-    }
-
-    fn visit(&mut self, valve: String) -> Self {
-        self.visited.insert(valve);
-        self.clone()
-    }
-}
-
 #[derive(Debug)]
 struct Valve {
     name: String,
@@ -212,171 +62,365 @@ fn parse(input: &str) -> IResult<&str, Vec<Valve>> {
     separated_list1(newline, parse_valves)(input).map(|(input, readings)| (input, readings))
 }
 
-fn shortest_path(connections: &HashMap<&str, Vec<String>>, name: &str, target: &str) -> u32 {
-    let mut visited: HashSet<String> = HashSet::new();
-    let mut queue = Vec::new();
-    let mut distance = HashMap::new();
+#[derive(Debug, Clone, PartialEq)]
+enum Action {
+    MoveTo(String),
+    Open(String),
+}
 
-    queue.push(name);
-    distance.insert(name, 0);
+// what an agent does during a minute, minutes are counted from 1 like in the puzzle
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    minute: u32,
+    agent: usize,
+    action: Action,
+}
 
-    while !queue.is_empty() {
-        let current = queue.remove(0);
-        visited.insert(current.to_string());
+#[derive(Debug, PartialEq)]
+enum ScheduleError {
+    UnknownValve(String),
+    OutOfTime(Step),
+    Busy(Step),
+    NoTunnel(Step),
+    NotAtValve(Step),
+    AlreadyOpen(Step),
+}
 
-        if current == target {
-            return distance[current];
+// replay a schedule against the valve graph, returning the pressure it releases, every agent
+// starts out at `start`
+fn validate(
+    valves: &[Valve],
+    start: &str,
+    minutes: u32,
+    schedule: &[Step],
+) -> Result<u32, ScheduleError> {
+    let valves = valves
+        .iter()
+        .map(|v| (v.name.as_str(), v))
+        .collect::<HashMap<_, _>>();
+    if !valves.contains_key(start) {
+        return Err(ScheduleError::UnknownValve(start.to_string()));
+    }
+    let mut steps = schedule.to_vec();
+    steps.sort_by_key(|step| (step.minute, step.agent));
+
+    let mut positions: HashMap<usize, &str> = HashMap::new();
+    let mut last_minute: HashMap<usize, u32> = HashMap::new();
+    let mut opened: Vec<&str> = vec![];
+    let mut pressure = 0;
+    for step in steps {
+        if step.minute == 0 || step.minute > minutes {
+            return Err(ScheduleError::OutOfTime(step));
         }
-
-        for neighbor in connections[current].iter() {
-            if !visited.contains(neighbor) {
-                queue.push(neighbor);
-                distance.insert(neighbor, distance[current] + 1);
+        if last_minute.insert(step.agent, step.minute) == Some(step.minute) {
+            return Err(ScheduleError::Busy(step));
+        }
+        let at = positions.entry(step.agent).or_insert(start);
+        match &step.action {
+            Action::MoveTo(to) => {
+                let to = valves
+                    .get_key_value(to.as_str())
+                    .map(|(name, _)| *name)
+                    .ok_or_else(|| ScheduleError::UnknownValve(to.clone()))?;
+                if !valves[*at].tunnels.iter().any(|tunnel| tunnel == to) {
+                    return Err(ScheduleError::NoTunnel(step));
+                }
+                *at = to;
+            }
+            Action::Open(valve) => {
+                if !valves.contains_key(valve.as_str()) {
+                    return Err(ScheduleError::UnknownValve(valve.clone()));
+                }
+                if valve != at {
+                    return Err(ScheduleError::NotAtValve(step));
+                }
+                if opened.contains(at) {
+                    return Err(ScheduleError::AlreadyOpen(step));
+                }
+                opened.push(at);
+                pressure += valves[*at].rate * (minutes - step.minute);
             }
         }
     }
+    Ok(pressure)
+}
 
-    0
+// the valve network reduced to the valves worth opening, with the travel time between every
+// pair of valves precomputed once and shared by all searches
+struct Network {
+    names: Vec<String>,
+    rates: Vec<u32>,
+    tunnels: Vec<Vec<usize>>,
+    distances: Vec<Vec<u32>>,
+    // first valve to move to on the shortest way from one valve to another
+    next: Vec<Vec<usize>>,
+    // valves with a non-zero rate, a set of opened valves is a bitmask over this list
+    useful: Vec<usize>,
+    start: usize,
 }
 
-fn day16a(path: &str, minutes: u32) -> u32 {
-    let content = fs::read_to_string(path).expect("file not found");
-    let (_, valves) = parse(content.as_str()).expect("parsing failed");
+impl Network {
+    fn new(valves: &[Valve], start: &str) -> Self {
+        let names = valves.iter().map(|v| v.name.clone()).collect::<Vec<_>>();
+        let index = names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| (name.as_str(), idx))
+            .collect::<HashMap<_, _>>();
 
-    let start = valves
-        .iter()
-        .find(|v| v.name == "AA")
-        .expect("no start valve");
+        let tunnels = valves
+            .iter()
+            .map(|v| v.tunnels.iter().map(|t| index[t.as_str()]).collect())
+            .collect::<Vec<Vec<_>>>();
+
+        // floyd-warshall over the tunnels
+        let n = valves.len();
+        let mut distances = vec![vec![u32::MAX / 2; n]; n];
+        let mut next = vec![(0..n).collect::<Vec<_>>(); n];
+        for (from, to) in tunnels.iter().enumerate() {
+            distances[from][from] = 0;
+            for &to in to {
+                distances[from][to] = 1;
+            }
+        }
+        for k in 0..n {
+            for i in 0..n {
+                for j in 0..n {
+                    let through = distances[i][k] + distances[k][j];
+                    if through < distances[i][j] {
+                        distances[i][j] = through;
+                        next[i][j] = next[i][k];
+                    }
+                }
+            }
+        }
 
-    let connections = valves
-        .iter()
-        .map(|v| (v.name.as_str(), v.connected_to()))
-        .collect::<HashMap<&str, Vec<String>>>();
+        let useful = valves
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.rate > 0)
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        assert!(useful.len() < 32, "too many valves with a flow rate");
+
+        Network {
+            rates: valves.iter().map(|v| v.rate).collect(),
+            start: *index.get(start).expect("no start valve"),
+            names,
+            tunnels,
+            distances,
+            next,
+            useful,
+        }
+    }
 
-    let scores = valves
-        .iter()
-        .map(|v| (v.name.as_str(), v.rate))
-        .collect::<HashMap<&str, u32>>();
+    // the most pressure a single agent can release by opening exactly the valves in each set,
+    // along with the order to open them in
+    fn best_per_set(&self, minutes: u32) -> (Vec<u32>, Vec<Vec<usize>>) {
+        let mut best = vec![0; 1 << self.useful.len()];
+        let mut routes = vec![vec![]; 1 << self.useful.len()];
+        let mut route = vec![];
+        self.explore(
+            self.start,
+            minutes,
+            0,
+            0,
+            &mut route,
+            &mut best,
+            &mut routes,
+        );
+        (best, routes)
+    }
 
-    let can_be_opened = valves
-        .iter()
-        .filter(|v| v.rate > 0)
-        .map(|v| v.name.clone())
-        .collect::<HashSet<String>>();
-
-    let mut weights = HashMap::new();
-    for valve in valves.iter() {
-        for valve_target in can_be_opened.iter() {
-            if valve.name == *valve_target {
+    #[allow(clippy::too_many_arguments)]
+    fn explore(
+        &self,
+        at: usize,
+        left: u32,
+        opened: usize,
+        pressure: u32,
+        route: &mut Vec<usize>,
+        best: &mut [u32],
+        routes: &mut [Vec<usize>],
+    ) {
+        if pressure > best[opened] {
+            best[opened] = pressure;
+            routes[opened] = route.clone();
+        }
+        for (bit, &valve) in self.useful.iter().enumerate() {
+            let cost = self.distances[at][valve] + 1;
+            if opened & (1 << bit) != 0 || cost >= left {
                 continue;
             }
-            let moves = shortest_path(&connections, &valve.name, valve_target);
-            weights.insert(
-                (valve.name.clone(), valve_target.clone()),
-                (moves, scores[valve_target.as_str()]),
+            let left = left - cost;
+            route.push(valve);
+            self.explore(
+                valve,
+                left,
+                opened | (1 << bit),
+                pressure + left * self.rates[valve],
+                route,
+                best,
+                routes,
             );
+            route.pop();
         }
     }
 
-    let mut best = 0;
-    let mut queue = vec![State::new(start.name.clone(), 0)];
-    while !queue.is_empty() {
-        let state = queue.pop().unwrap();
-        let candidates: Vec<_> = can_be_opened
-            .iter()
-            .filter(|&v| weights.contains_key(&(state.name.clone(), v.clone())))
-            .filter(|v| !state.visited.contains(*v))
-            .map(|v| (v, weights[&(state.name.clone(), v.clone())]))
-            .filter(|(_, (moves, _))| state.minute + moves + 1 <= minutes)
-            .map(|(n, (moves, rate))| {
-                State {
-                    name: n.clone(),
-                    score: state.score + (rate * (minutes - state.minute - moves - 1)),
-                    minute: state.minute + moves + 1,
-                    visited: state.visited.clone(),
-                }
-                .visit(n.clone())
-            })
-            .filter(|s| s.minute <= minutes)
-            .collect();
-
-        for target in candidates.iter() {
-            queue.push(target.clone());
-            best = cmp::max(best, target.score);
+    // the most pressure `agents` working in parallel can release, each one opening a disjoint
+    // set of valves, along with the valves each agent opens in order
+    fn best_routes(&self, minutes: u32, agents: usize) -> (u32, Vec<Vec<usize>>) {
+        let all = (1 << self.useful.len()) - 1;
+        if agents == 0 {
+            return (0, vec![]);
         }
-    }
 
-    best
-}
+        // let every set also count the best of its subsets, an agent may leave valves closed
+        let (mut single, routes) = self.best_per_set(minutes);
+        let mut exact = (0..=all).collect::<Vec<usize>>();
+        for bit in 0..self.useful.len() {
+            for set in 0..=all {
+                if set & (1 << bit) != 0 && single[set ^ (1 << bit)] > single[set] {
+                    single[set] = single[set ^ (1 << bit)];
+                    exact[set] = exact[set ^ (1 << bit)];
+                }
+            }
+        }
 
-fn day16b(path: &str, minutes: u32) -> u32 {
-    let content = fs::read_to_string(path).expect("file not found");
-    let (_, valves) = parse(content.as_str()).expect("parsing failed");
+        // split the valves between one agent and the rest of the team, one agent at a time
+        let mut team = single.clone();
+        let mut splits = vec![];
+        for agent in 2..=agents {
+            let sets = match agent == agents {
+                true => all..=all,
+                false => 0..=all,
+            };
+            // leaving this agent idle is always an option
+            let mut next = team.clone();
+            let mut split = vec![0; all + 1];
+            for set in sets {
+                let mut own = set;
+                while own != 0 {
+                    if single[own] + team[set ^ own] > next[set] {
+                        next[set] = single[own] + team[set ^ own];
+                        split[set] = own;
+                    }
+                    own = (own - 1) & set;
+                }
+            }
+            team = next;
+            splits.push(split);
+        }
 
-    let start = valves
-        .iter()
-        .find(|v| v.name == "AA")
-        .expect("no start valve");
+        let mut set = all;
+        let mut assigned = vec![];
+        for split in splits.iter().rev() {
+            assigned.push(split[set]);
+            set ^= split[set];
+        }
+        assigned.push(set);
+        assigned.reverse();
 
-    let connections = valves
-        .iter()
-        .map(|v| (v.name.as_str(), v.connected_to()))
-        .collect::<HashMap<&str, Vec<String>>>();
+        let routes = assigned
+            .into_iter()
+            .map(|set| routes[exact[set]].clone())
+            .collect();
+        (team[all], routes)
+    }
 
-    let scores = valves
-        .iter()
-        .map(|v| (v.name.as_str(), v.rate))
-        .collect::<HashMap<&str, u32>>();
+    fn max_pressure(&self, minutes: u32, agents: usize) -> u32 {
+        self.best_routes(minutes, agents).0
+    }
 
-    let can_be_opened = valves
-        .iter()
-        .filter(|v| v.rate > 0)
-        .map(|v| v.name.clone())
-        .collect::<HashSet<String>>();
-
-    let mut weights = HashMap::new();
-    for valve in valves.iter() {
-        for valve_target in can_be_opened.iter() {
-            if valve.name == *valve_target {
-                continue;
+    // the optimal plan as a minute by minute timeline of every agent's moves and openings
+    fn schedule(&self, minutes: u32, agents: usize) -> (u32, Vec<Step>) {
+        let (pressure, routes) = self.best_routes(minutes, agents);
+        let mut steps = vec![];
+        for (agent, route) in routes.into_iter().enumerate() {
+            let mut at = self.start;
+            let mut minute = 0;
+            for valve in route {
+                while at != valve {
+                    at = self.next[at][valve];
+                    minute += 1;
+                    steps.push(Step {
+                        minute,
+                        agent,
+                        action: Action::MoveTo(self.names[at].clone()),
+                    });
+                }
+                minute += 1;
+                steps.push(Step {
+                    minute,
+                    agent,
+                    action: Action::Open(self.names[valve].clone()),
+                });
             }
-            let moves = shortest_path(&connections, &valve.name, valve_target);
-            weights.insert(
-                (valve.name.clone(), valve_target.clone()),
-                (moves, scores[valve_target.as_str()]),
-            );
         }
+        steps.sort_by_key(|step| (step.minute, step.agent));
+        (pressure, steps)
     }
 
-    let mut next_queue = vec![ElephantState {
-        name: start.name.clone(),
-        elephant: start.name.clone(),
-        minute: 1,
-        elephant_minute: 1,
-        score: 0,
-        visited: HashSet::from([start.name.clone()]),
-    }];
-
-    let mut best = 0;
-    for minute in 1..=minutes {
-        let mut queue = next_queue.clone();
-        next_queue.clear();
-
-        while !queue.is_empty() {
-            let mut state = queue.pop().unwrap();
-            best = cmp::max(best, state.score);
-             
-            state.score(minutes, minute, &scores);
-            for states in state.try_move(minute, &can_be_opened, &weights) {
-                match states {
-                    Some(st) => next_queue.push(st),
-                    None => next_queue.push(state.clone()),
+    // graphviz description of the network, the reduced graph only keeps the start and the
+    // valves worth opening, connected by their travel times
+    fn to_dot(&self, reduced: bool) -> String {
+        let mut dot = String::from("graph valves {\n");
+        let nodes = match reduced {
+            true => [self.start]
+                .into_iter()
+                .chain(self.useful.iter().copied().filter(|&v| v != self.start))
+                .collect::<Vec<_>>(),
+            false => (0..self.names.len()).collect(),
+        };
+        for &node in nodes.iter() {
+            let shape = match node == self.start {
+                true => "doublecircle",
+                false => "circle",
+            };
+            writeln!(
+                dot,
+                "  {} [label=\"{}\\n{}\", shape={}];",
+                self.names[node], self.names[node], self.rates[node], shape
+            )
+            .unwrap();
+        }
+        for (idx, &from) in nodes.iter().enumerate() {
+            match reduced {
+                true => {
+                    for &to in nodes[idx + 1..].iter() {
+                        writeln!(
+                            dot,
+                            "  {} -- {} [label=\"{}\"];",
+                            self.names[from], self.names[to], self.distances[from][to]
+                        )
+                        .unwrap();
+                    }
+                }
+                false => {
+                    // tunnels go both ways, only draw them once
+                    for &to in self.tunnels[from].iter().filter(|&&to| from < to) {
+                        writeln!(dot, "  {} -- {};", self.names[from], self.names[to]).unwrap();
+                    }
                 }
             }
         }
+        dot.push_str("}\n");
+        dot
     }
+}
+
+fn day16a(path: &str, start: &str, minutes: u32) -> u32 {
+    let content = fs::read_to_string(path).expect("file not found");
+    let (_, valves) = parse(content.as_str()).expect("parsing failed");
 
-    best
+    Network::new(&valves, start).max_pressure(minutes, 1)
+}
+
+fn day16b(path: &str, start: &str, minutes: u32) -> u32 {
+    let content = fs::read_to_string(path).expect("file not found");
+    let (_, valves) = parse(content.as_str()).expect("parsing failed");
+
+    Network::new(&valves, start).max_pressure(minutes, 2)
 }
 
 #[cfg(test)]
@@ -385,27 +429,186 @@ mod tests {
 
     #[test]
     fn find_most_pressure() {
-        let actual = day16a("./data/day16.txt", 30);
+        let actual = day16a("./data/day16.txt", "AA", 30);
         assert_eq!(actual, 1651);
     }
 
     #[test]
-    #[ignore = "slow test"]
     fn find_most_pressure_part_a() {
-        let actual = day16a("./data/day16final.txt", 30);
+        let actual = day16a("./data/day16final.txt", "AA", 30);
         assert_eq!(actual, 1792);
     }
 
     #[test]
     fn find_most_pressure_with_elephant() {
-        let actual = day16b("./data/day16.txt", 26);
+        let actual = day16b("./data/day16.txt", "AA", 26);
         assert_eq!(actual, 1707);
     }
 
     #[test]
-    #[ignore = "slow test"]
     fn find_most_pressure_with_elephant_part_b() {
-        let actual = day16b("./data/day16final.txt", 26);
+        let actual = day16b("./data/day16final.txt", "AA", 26);
         assert_eq!(actual, 2587);
     }
+
+    #[test]
+    fn find_most_pressure_with_more_agents() {
+        let content = fs::read_to_string("./data/day16.txt").expect("file not found");
+        let (_, valves) = parse(content.as_str()).expect("parsing failed");
+        let network = Network::new(&valves, "AA");
+
+        assert_eq!(network.max_pressure(30, 0), 0);
+        assert_eq!(network.max_pressure(30, 1), 1651);
+        assert_eq!(network.max_pressure(26, 2), 1707);
+        // extra agents can only help, until there are more of them than valves worth opening
+        let three = network.max_pressure(26, 3);
+        let four = network.max_pressure(26, 4);
+        assert!(three >= 1707);
+        assert!(four >= three);
+        assert_eq!(network.max_pressure(26, 6), network.max_pressure(26, 7));
+    }
+
+    #[test]
+    fn replay_schedule() {
+        let content = fs::read_to_string("./data/day16.txt").expect("file not found");
+        let (_, valves) = parse(content.as_str()).expect("parsing failed");
+        let network = Network::new(&valves, "AA");
+
+        let (pressure, schedule) = network.schedule(30, 1);
+        assert_eq!(pressure, 1651);
+        assert_eq!(validate(&valves, "AA", 30, &schedule), Ok(1651));
+        let opened = schedule
+            .iter()
+            .filter_map(|step| match &step.action {
+                Action::Open(valve) => Some((step.minute, valve.as_str())),
+                Action::MoveTo(_) => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            opened,
+            vec![
+                (2, "DD"),
+                (5, "BB"),
+                (9, "JJ"),
+                (17, "HH"),
+                (21, "EE"),
+                (24, "CC")
+            ]
+        );
+
+        for agents in 2..=3 {
+            let (pressure, schedule) = network.schedule(26, agents);
+            assert_eq!(validate(&valves, "AA", 26, &schedule), Ok(pressure));
+        }
+
+        let content = fs::read_to_string("./data/day16final.txt").expect("file not found");
+        let (_, valves) = parse(content.as_str()).expect("parsing failed");
+        let (pressure, schedule) = Network::new(&valves, "AA").schedule(26, 2);
+        assert_eq!(pressure, 2587);
+        assert_eq!(validate(&valves, "AA", 26, &schedule), Ok(2587));
+    }
+
+    #[test]
+    fn start_from_other_valve() {
+        let content = fs::read_to_string("./data/day16.txt").expect("file not found");
+        let (_, valves) = parse(content.as_str()).expect("parsing failed");
+        let network = Network::new(&valves, "JJ");
+
+        // opening JJ right away beats the best plan from AA
+        let (pressure, schedule) = network.schedule(30, 1);
+        assert!(pressure > 1651);
+        assert_eq!(
+            schedule[0],
+            Step {
+                minute: 1,
+                agent: 0,
+                action: Action::Open("JJ".to_string())
+            }
+        );
+        assert_eq!(validate(&valves, "JJ", 30, &schedule), Ok(pressure));
+        assert_eq!(day16a("./data/day16.txt", "JJ", 30), pressure);
+    }
+
+    #[test]
+    fn export_dot() {
+        let content = fs::read_to_string("./data/day16.txt").expect("file not found");
+        let (_, valves) = parse(content.as_str()).expect("parsing failed");
+        let network = Network::new(&valves, "AA");
+
+        let full = network.to_dot(false);
+        assert!(full.starts_with("graph valves {\n"));
+        assert!(full.contains("  AA [label=\"AA\\n0\", shape=doublecircle];"));
+        assert!(full.contains("  HH [label=\"HH\\n22\", shape=circle];"));
+        assert!(full.contains("  AA -- DD;"));
+        assert!(!full.contains("  DD -- AA;"));
+        assert_eq!(full.matches(" -- ").count(), 10);
+
+        let reduced = network.to_dot(true);
+        assert!(!reduced.contains("  FF ["));
+        assert!(reduced.contains("  AA -- HH [label=\"5\"];"));
+        // start plus six valves worth opening, all connected to each other
+        assert_eq!(reduced.matches(" -- ").count(), 21);
+    }
+
+    #[test]
+    fn reject_invalid_schedules() {
+        let content = fs::read_to_string("./data/day16.txt").expect("file not found");
+        let (_, valves) = parse(content.as_str()).expect("parsing failed");
+        let step = |minute, agent, action| Step {
+            minute,
+            agent,
+            action,
+        };
+        let move_to = |valve: &str| Action::MoveTo(valve.to_string());
+        let open = |valve: &str| Action::Open(valve.to_string());
+
+        assert_eq!(
+            validate(
+                &valves,
+                "AA",
+                30,
+                &[step(1, 0, move_to("DD")), step(2, 0, open("DD"))]
+            ),
+            Ok(20 * 28)
+        );
+        assert_eq!(
+            validate(&valves, "AA", 30, &[step(1, 0, move_to("CC"))]),
+            Err(ScheduleError::NoTunnel(step(1, 0, move_to("CC"))))
+        );
+        assert_eq!(
+            validate(&valves, "AA", 30, &[step(1, 0, open("DD"))]),
+            Err(ScheduleError::NotAtValve(step(1, 0, open("DD"))))
+        );
+        assert_eq!(
+            validate(&valves, "AA", 30, &[step(1, 0, move_to("ZZ"))]),
+            Err(ScheduleError::UnknownValve("ZZ".to_string()))
+        );
+        assert_eq!(
+            validate(
+                &valves,
+                "AA",
+                30,
+                &[step(1, 0, move_to("DD")), step(1, 0, open("DD"))]
+            ),
+            Err(ScheduleError::Busy(step(1, 0, open("DD"))))
+        );
+        assert_eq!(
+            validate(
+                &valves,
+                "AA",
+                30,
+                &[
+                    step(1, 0, move_to("DD")),
+                    step(1, 1, move_to("DD")),
+                    step(2, 0, open("DD")),
+                    step(2, 1, open("DD"))
+                ]
+            ),
+            Err(ScheduleError::AlreadyOpen(step(2, 1, open("DD"))))
+        );
+        assert_eq!(
+            validate(&valves, "AA", 2, &[step(3, 0, move_to("DD"))]),
+            Err(ScheduleError::OutOfTime(step(3, 0, move_to("DD"))))
+        );
+    }
 }
@@ -2,193 +2,318 @@
 
 use core::fmt;
 use std::{
-    collections::HashSet,
+    collections::VecDeque,
     fmt::{Display, Formatter},
     fs,
 };
 
+// the five rocks of the puzzle, top row first
+const ROCKS: &str = "####
+
+.#.
+###
+.#.
+
+..#
+..#
+###
+
+#
+#
+#
+#
+
+##
+##";
+
+// wide enough for any chamber up to 128 columns
+type Row = u128;
+
+#[derive(Debug, PartialEq)]
+enum ChamberError {
+    // chambers are 1 to 128 columns wide
+    Width(i32),
+    // rocks appear at or right of the left wall and at least one row above the tower
+    Spawn((i32, i32)),
+    // a rock of this width does not fit between the spawn gap and the right wall
+    NoRoom(i32),
+    // a rock wider than any chamber, or one without a single '#'
+    Rock(i32),
+}
+
 struct Chamber {
-    bricks: HashSet<(i32, i32)>,
+    // one bitmask per row still reachable from above, lowest first, bit 0 is the leftmost column
+    rows: VecDeque<Row>,
+    // rows below `rows[0]` that were dropped because no rock can reach them anymore
+    base: i32,
 
     height: i32,
     width: i32,
+    // where a new rock appears, as the gap to the left wall and the distance from the top of
+    // the tower to the rock's bottom row
+    spawn: (i32, i32),
 
     position: (i32, i32),
 }
 
-#[derive(Debug)]
-enum Brick {
-    Straight(Vec<(i32, i32)>, i32, i32),
-    Cross(Vec<(i32, i32)>, i32, i32),
-    L(Vec<(i32, i32)>, i32, i32),
-    I(Vec<(i32, i32)>, i32, i32),
-    Square(Vec<(i32, i32)>, i32, i32),
+#[derive(Debug, PartialEq)]
+struct Brick {
+    // cells measured from the top left corner
+    bricks: Vec<(i32, i32)>,
+    // the same cells as one bitmask per row, top row first
+    masks: Vec<Row>,
+    height: i32,
+    width: i32,
 }
 
 impl Brick {
     fn height(&self) -> i32 {
-        match self {
-            Brick::Straight(_, height, _) => *height,
-            Brick::Cross(_, height, _) => *height,
-            Brick::L(_, height, _) => *height,
-            Brick::I(_, height, _) => *height,
-            Brick::Square(_, height, _) => *height,
-        }
+        self.height
     }
 
     fn width(&self) -> i32 {
-        match self {
-            Brick::Straight(_, _, width) => *width,
-            Brick::Cross(_, _, width) => *width,
-            Brick::L(_, _, width) => *width,
-            Brick::I(_, _, width) => *width,
-            Brick::Square(_, _, width) => *width,
-        }
+        self.width
     }
 
     fn bricks(&self) -> &Vec<(i32, i32)> {
-        match self {
-            Brick::Straight(bricks, _, _) => bricks,
-            Brick::Cross(bricks, _, _) => bricks,
-            Brick::L(bricks, _, _) => bricks,
-            Brick::I(bricks, _, _) => bricks,
-            Brick::Square(bricks, _, _) => bricks,
-        }
+        &self.bricks
     }
 }
 
+// rocks drawn with '#' for solid cells and '.' for gaps, separated by blank lines
+fn parse_bricks(input: &str) -> Result<Vec<Brick>, ChamberError> {
+    input
+        .trim()
+        .split("\n\n")
+        .map(|shape| {
+            let bricks = shape
+                .lines()
+                .enumerate()
+                .flat_map(|(j, line)| {
+                    line.trim()
+                        .chars()
+                        .enumerate()
+                        .filter(|&(_, c)| c == '#')
+                        .map(move |(i, _)| (i as i32, j as i32))
+                })
+                .collect::<Vec<_>>();
+            let height = bricks.iter().map(|&(_, j)| j + 1).max().unwrap_or(0);
+            let width = bricks.iter().map(|&(i, _)| i + 1).max().unwrap_or(0);
+            if !(1..=Row::BITS as i32).contains(&width) {
+                return Err(ChamberError::Rock(width));
+            }
+            let masks = (0..height)
+                .map(|row| {
+                    bricks
+                        .iter()
+                        .filter(|&&(_, j)| j == row)
+                        .fold(0, |mask, &(i, _)| mask | 1 << i)
+                })
+                .collect();
+            Ok(Brick {
+                bricks,
+                masks,
+                height,
+                width,
+            })
+        })
+        .collect()
+}
+
 impl Chamber {
+    fn new(width: i32, spawn: (i32, i32)) -> Result<Self, ChamberError> {
+        if !(1..=Row::BITS as i32).contains(&width) {
+            return Err(ChamberError::Width(width));
+        }
+        let (left, above) = spawn;
+        if left < 0 || above < 1 {
+            return Err(ChamberError::Spawn(spawn));
+        }
+        Ok(Chamber {
+            rows: VecDeque::new(),
+            base: 0,
+            height: 0,
+            width,
+            spawn,
+            position: (0, 0),
+        })
+    }
+
+    // the bitmask of row `y`, the floor and the dropped rows count as solid
+    fn row(&self, y: i32) -> Row {
+        if y <= self.base {
+            return Row::MAX;
+        }
+        self.rows
+            .get((y - self.base - 1) as usize)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // whether the brick overlaps anything with its top left corner at (x, y)
+    fn collides(&self, brick: &Brick, (x, y): (i32, i32)) -> bool {
+        if x < 1 || x + brick.width() - 1 > self.width {
+            return true;
+        }
+        brick
+            .masks
+            .iter()
+            .enumerate()
+            .any(|(j, mask)| self.row(y - j as i32) & (mask << (x - 1)) != 0)
+    }
+
     fn left(&mut self, brick: &Brick) {
         let (x, y) = self.position;
-        let candidates = brick
-            .bricks()
-            .iter()
-            .map(|&(i, j)| (x + i - 1, y - j))
-            .collect::<HashSet<_>>();
-        if candidates.iter().any(|&(i, _)| i < 1) {
-            return;
-        }
-        if self.bricks.intersection(&candidates).count() == 0 {
+        if !self.collides(brick, (x - 1, y)) {
             self.position = (x - 1, y);
         }
     }
 
     fn right(&mut self, brick: &Brick) {
         let (x, y) = self.position;
-        let candidates = brick
-            .bricks()
-            .iter()
-            .map(|&(i, j)| (x + i + 1, y - j))
-            .collect::<HashSet<_>>();
-        if candidates.iter().any(|&(i, _)| i > 7) {
-            return;
-        }
-        if self.bricks.intersection(&candidates).count() == 0 {
+        if !self.collides(brick, (x + 1, y)) {
             self.position = (x + 1, y);
         }
     }
 
     fn down(&mut self, brick: &Brick) -> bool {
         let (x, y) = self.position;
-        let candidates = brick
-            .bricks()
-            .iter()
-            .map(|&(i, j)| (x + i, y - j - 1))
-            .collect::<HashSet<_>>();
-        if candidates.iter().any(|&(_, j)| j < 1) {
+        if self.collides(brick, (x, y - 1)) {
             return false;
         }
-        if self.bricks.intersection(&candidates).count() == 0 {
-            self.position = (x, y - 1);
-            return true;
-        }
-
-        false
+        self.position = (x, y - 1);
+        true
     }
 
     fn reset_position(&mut self, brick: &Brick) {
-        // position is measured from the top left corner
-        self.position = (3, self.height + brick.height() + 3);
+        // position is measured from the top left corner, the left wall sits at x = 0
+        let (left, above) = self.spawn;
+        self.position = (left + 1, self.height + above + brick.height() - 1);
     }
 
-    fn store_brick(&mut self, brick: &mut Brick) {
+    fn store_brick(&mut self, brick: &Brick) {
         let (x, y) = self.position;
-        let bricks = brick.bricks();
-        for &(i, j) in bricks {
-            self.bricks.insert((i + x, y - j));
+        for (j, mask) in brick.masks.iter().enumerate() {
+            let idx = (y - j as i32 - self.base - 1) as usize;
+            if idx >= self.rows.len() {
+                self.rows.resize(idx + 1, 0);
+            }
+            self.rows[idx] |= mask << (x - 1);
+        }
+        self.height = self.base + self.rows.len() as i32;
+        self.drop_unreachable();
+    }
+
+    // flood the empty cells from above the tower and forget every row below the lowest one
+    // the air can reach
+    fn drop_unreachable(&mut self) {
+        let full = Row::MAX >> (Row::BITS as i32 - self.width);
+        let mut reached: Vec<Row> = vec![0; self.rows.len() + 1];
+        reached[self.rows.len()] = full;
+        let mut lowest = self.rows.len();
+        let mut stack = vec![self.rows.len()];
+        while let Some(row) = stack.pop() {
+            let open = |row: usize| match row < self.rows.len() {
+                true => !self.rows[row] & full,
+                false => full,
+            };
+            // spread sideways within the row
+            let mut mask = reached[row];
+            loop {
+                let spread = (mask | mask << 1 | mask >> 1) & open(row);
+                if spread == mask {
+                    break;
+                }
+                mask = spread;
+            }
+            reached[row] = mask;
+            for next in [row.wrapping_sub(1), row + 1] {
+                if next >= reached.len() {
+                    continue;
+                }
+                let flow = mask & open(next) & !reached[next];
+                if flow != 0 {
+                    reached[next] |= flow;
+                    lowest = lowest.min(next);
+                    stack.push(next);
+                }
+            }
         }
-        self.height = self.bricks.iter().map(|&(_, j)| j).max().unwrap();
+        self.rows.drain(..lowest);
+        self.base += lowest as i32;
     }
 }
 
 impl Display for Chamber {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let mut s = String::new();
-        for y in (0..=self.height).rev() {
-            for x in 0..=8 {
-                if (x == 0 || x == 8) && y == 0 {
-                    s.push_str("+");
+        for y in (self.base..=self.height).rev() {
+            for x in 0..=self.width + 1 {
+                let wall = x == 0 || x == self.width + 1;
+                if wall && y == 0 {
+                    s.push('+');
                     continue;
-                } else if x == 0 || x == 8 {
-                    s.push_str("|");
+                } else if wall {
+                    s.push('|');
                     continue;
                 } else if y == 0 {
-                    s.push_str("-");
+                    s.push('-');
+                    continue;
+                } else if y == self.base {
+                    // rows below this one are no longer stored
+                    s.push('~');
                     continue;
                 }
-                if self.bricks.contains(&(x, y)) {
-                    s.push_str("#");
+                if self.row(y) & (1 << (x - 1)) != 0 {
+                    s.push('#');
                 } else {
-                    s.push_str(".");
+                    s.push('.');
                 }
             }
-            s.push_str("\n");
+            s.push('\n');
         }
         write!(f, "{}", s)
     }
 }
 
 fn load_bricks() -> Vec<Brick> {
-    vec![
-        Brick::Straight(vec![(0, 0), (1, 0), (2, 0), (3, 0)], 1, 4),
-        Brick::Cross(vec![(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)], 3, 3),
-        Brick::L(vec![(2, 0), (2, 1), (2, 2), (1, 2), (0, 2)], 3, 3),
-        Brick::I(vec![(0, 0), (0, 1), (0, 2), (0, 3)], 4, 1),
-        Brick::Square(vec![(0, 0), (0, 1), (1, 0), (1, 1)], 2, 2),
-    ]
+    parse_bricks(ROCKS).expect("the puzzle's rocks are valid")
 }
 
-fn day17a(path: &str, target: usize) -> usize {
-    let wind = fs::read_to_string(path)
-        .expect("file not found")
-        .trim()
-        .to_string();
+// drop `target` rocks, cycling through `bricks` and the jet pattern, returning the tower height
+fn simulate(
+    wind: &str,
+    bricks: &[Brick],
+    chamber: &mut Chamber,
+    target: usize,
+) -> Result<usize, ChamberError> {
+    let room = chamber.width - chamber.spawn.0;
+    if let Some(brick) = bricks.iter().find(|brick| brick.width() > room) {
+        return Err(ChamberError::NoRoom(brick.width()));
+    }
 
-    let wind_len = wind.len();
-    let mut bricks = load_bricks();
+    let jets = wind
+        .bytes()
+        .map(|direction| match direction {
+            b'<' => -1,
+            b'>' => 1,
+            _ => panic!("invalid wind direction"),
+        })
+        .collect::<Vec<i8>>();
     let bricks_len = bricks.len();
 
-    let mut chamber = Chamber {
-        bricks: HashSet::new(),
-        height: 0,
-        width: 7,
-        position: (2, 4),
-    };
-
     let mut brick_idx: usize = 0;
     let mut wind_idx: usize = 0;
 
     while target > brick_idx {
-        let brick = bricks.get_mut(brick_idx % bricks_len).unwrap();
+        let brick = &bricks[brick_idx % bricks_len];
         chamber.reset_position(brick);
         loop {
-            let direction = wind.chars().nth(wind_idx % wind_len).unwrap();
-            wind_idx += 1;
-            match direction {
-                '<' => chamber.left(brick),
-                '>' => chamber.right(brick),
-                _ => panic!("invalid wind direction"),
+            match jets[wind_idx] {
+                -1 => chamber.left(brick),
+                _ => chamber.right(brick),
             };
+            wind_idx = (wind_idx + 1) % jets.len();
 
             if !chamber.down(brick) {
                 break;
@@ -199,7 +324,22 @@ fn day17a(path: &str, target: usize) -> usize {
         chamber.store_brick(brick);
     }
 
-    chamber.height as usize
+    Ok(chamber.height as usize)
+}
+
+fn day17a(path: &str, target: usize) -> usize {
+    let wind = fs::read_to_string(path)
+        .expect("file not found")
+        .trim()
+        .to_string();
+
+    simulate(
+        &wind,
+        &load_bricks(),
+        &mut Chamber::new(7, (2, 4)).expect("invalid chamber"),
+        target,
+    )
+    .expect("rocks do not fit")
 }
 
 #[cfg(test)]
@@ -221,6 +361,119 @@ mod tests {
         assert_eq!(actual, 1514285714288);
     }
 
+    #[test]
+    fn load_default_rocks() {
+        let bricks = load_bricks();
+        assert_eq!(bricks.len(), 5);
+        assert_eq!(
+            bricks[2],
+            Brick {
+                bricks: vec![(2, 0), (2, 1), (0, 2), (1, 2), (2, 2)],
+                masks: vec![0b100, 0b100, 0b111],
+                height: 3,
+                width: 3,
+            }
+        );
+        assert_eq!((bricks[3].height(), bricks[3].width()), (4, 1));
+
+        assert_eq!(parse_bricks(&"#".repeat(129)), Err(ChamberError::Rock(129)));
+        assert_eq!(parse_bricks("##\n\n.."), Err(ChamberError::Rock(0)));
+    }
+
+    #[test]
+    fn find_height_with_custom_rules() {
+        let wind = fs::read_to_string("./data/day17.txt").expect("file not found");
+        let wind = wind.trim();
+
+        // rocks spanning the whole chamber simply stack up
+        let bricks = parse_bricks("####\n\n####\n####").unwrap();
+        assert_eq!(
+            simulate(wind, &bricks, &mut Chamber::new(4, (0, 4)).unwrap(), 10).unwrap(),
+            15
+        );
+
+        // a single cell rock in a one wide chamber ignores the wind
+        let bricks = parse_bricks("#").unwrap();
+        assert_eq!(
+            simulate(wind, &bricks, &mut Chamber::new(1, (0, 1)).unwrap(), 7).unwrap(),
+            7
+        );
+
+        let mut chamber = Chamber::new(7, (2, 4)).unwrap();
+        chamber.reset_position(&load_bricks()[0]);
+        assert_eq!(chamber.position, (3, 4));
+        let mut chamber = Chamber::new(7, (0, 1)).unwrap();
+        chamber.reset_position(&load_bricks()[3]);
+        assert_eq!(chamber.position, (1, 4));
+    }
+
+    #[test]
+    fn find_height_in_wide_chambers() {
+        let wind = fs::read_to_string("./data/day17.txt").expect("file not found");
+        let wind = wind.trim();
+
+        let bricks = parse_bricks(&"#".repeat(12)).unwrap();
+        let mut chamber = Chamber::new(12, (0, 4)).unwrap();
+        assert_eq!(simulate(wind, &bricks, &mut chamber, 10).unwrap(), 10);
+
+        // the default rocks still fall in a chamber wider than a u8
+        let mut chamber = Chamber::new(100, (2, 4)).unwrap();
+        let height = simulate(wind, &load_bricks(), &mut chamber, 2022).unwrap();
+        assert!(height > 0 && height < 3068);
+
+        assert_eq!(Chamber::new(0, (2, 4)).err(), Some(ChamberError::Width(0)));
+        assert_eq!(
+            Chamber::new(200, (2, 4)).err(),
+            Some(ChamberError::Width(200))
+        );
+    }
+
+    #[test]
+    fn reject_bad_spawn_gaps() {
+        let wind = fs::read_to_string("./data/day17.txt").expect("file not found");
+        let wind = wind.trim();
+
+        assert_eq!(
+            Chamber::new(7, (-1, 4)).err(),
+            Some(ChamberError::Spawn((-1, 4)))
+        );
+        assert_eq!(
+            Chamber::new(7, (2, 0)).err(),
+            Some(ChamberError::Spawn((2, 0)))
+        );
+
+        // the flat rock needs four columns, only one is left right of the gap
+        let mut chamber = Chamber::new(7, (6, 4)).unwrap();
+        assert_eq!(
+            simulate(wind, &load_bricks(), &mut chamber, 1),
+            Err(ChamberError::NoRoom(4))
+        );
+        let mut chamber = Chamber::new(7, (3, 4)).unwrap();
+        assert_eq!(simulate(wind, &load_bricks(), &mut chamber, 1), Ok(1));
+    }
+
+    #[test]
+    fn keep_only_reachable_rows() {
+        let wind = fs::read_to_string("./data/day17.txt").expect("file not found");
+        let mut chamber = Chamber::new(7, (2, 4)).unwrap();
+        let height = simulate(wind.trim(), &load_bricks(), &mut chamber, 2022).unwrap();
+        assert_eq!(height, 3068);
+        assert!(chamber.rows.len() < 100);
+        assert_eq!(chamber.base + chamber.rows.len() as i32, 3068);
+
+        // full rows cut the tower off right away
+        let mut chamber = Chamber::new(4, (0, 4)).unwrap();
+        simulate(
+            wind.trim(),
+            &parse_bricks("####").unwrap(),
+            &mut chamber,
+            10,
+        )
+        .unwrap();
+        assert_eq!((chamber.base, chamber.rows.len()), (10, 0));
+        assert_eq!(chamber.to_string(), "|~~~~|\n");
+    }
+
     #[test]
     fn find_height_part_a() {
         let actual = day17a("./data/day17final.txt", 2022);
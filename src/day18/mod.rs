@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Write,
     fs::{self},
 };
 
@@ -61,57 +62,243 @@ fn day18a(path: &str) -> usize {
     surface_area(&cubes)
 }
 
-fn day18b(path: &str) -> usize {
-    let content = fs::read_to_string(path).expect("file not found");
-    let (_, cubes) = parse(content.as_str()).expect("parsing failed");
-    let ((x_min, y_min, z_min), (x_max, y_max, z_max)) = universe(&cubes);
-    let mut air = HashSet::new();
-
-    // steam outside of lava droplet
-    let mut queue = VecDeque::from([(x_min, y_min, z_min)]);
-    let mut steam = HashSet::new();
-    while !queue.is_empty() {
-        let next = queue.pop_front().unwrap();
-
-        for (x, y, z) in neighbours(&next) {
-            let n = (x, y, z);
-            if x < x_min || x > x_max || y < y_min || y > y_max || z < z_min || z > z_max {
-                continue;
+// a square side of a cube, corners in counter clockwise order seen from the side the normal
+// points to
+#[derive(Debug, PartialEq)]
+struct Face {
+    corners: [Cube; 4],
+    normal: Cube,
+}
+
+impl Face {
+    // the side of `cube` that touches `neighbour`
+    fn between(cube: &Cube, neighbour: &Cube) -> Face {
+        let at = [cube.0, cube.1, cube.2];
+        let normal = [
+            neighbour.0 - cube.0,
+            neighbour.1 - cube.1,
+            neighbour.2 - cube.2,
+        ];
+        let axis = normal.iter().position(|&d| d != 0).unwrap();
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        let offsets = match normal[axis] > 0 {
+            true => [(0, 0), (1, 0), (1, 1), (0, 1)],
+            false => [(0, 0), (0, 1), (1, 1), (1, 0)],
+        };
+        let corners = offsets.map(|(du, dv)| {
+            let mut corner = at;
+            if normal[axis] > 0 {
+                corner[axis] += 1;
             }
-            if steam.contains(&n) {
-                continue;
+            corner[u] += du;
+            corner[v] += dv;
+            (corner[0], corner[1], corner[2])
+        });
+        Face {
+            corners,
+            normal: (normal[0], normal[1], normal[2]),
+        }
+    }
+}
+
+// split cells into groups connected through their faces
+fn components(cells: &HashSet<Cube>) -> Vec<HashSet<Cube>> {
+    let mut seen = HashSet::new();
+    let mut groups = vec![];
+    for &cell in cells {
+        if !seen.insert(cell) {
+            continue;
+        }
+        let mut group = HashSet::from([cell]);
+        let mut queue = VecDeque::from([cell]);
+        while let Some(next) = queue.pop_front() {
+            for n in neighbours(&next) {
+                if cells.contains(&n) && seen.insert(n) {
+                    group.insert(n);
+                    queue.push_back(n);
+                }
             }
-            if cubes.get(&n).is_none() {
-                steam.insert(n);
-                queue.push_back(n);
+        }
+        groups.push(group);
+    }
+    groups
+}
+
+struct Droplet {
+    cubes: HashSet<Cube>,
+    // steam filling the bounding box around the droplet, padded by one on every side
+    steam: HashSet<Cube>,
+    min: Cube,
+    max: Cube,
+}
+
+impl Droplet {
+    fn new(cubes: HashSet<Cube>) -> Self {
+        let (min, max) = universe(&cubes);
+        let ((x_min, y_min, z_min), (x_max, y_max, z_max)) = (min, max);
+
+        // steam outside of lava droplet
+        let mut queue = VecDeque::from([min]);
+        let mut steam = HashSet::from([min]);
+        while let Some(next) = queue.pop_front() {
+            for (x, y, z) in neighbours(&next) {
+                let n = (x, y, z);
+                if x < x_min || x > x_max || y < y_min || y > y_max || z < z_min || z > z_max {
+                    continue;
+                }
+                if !cubes.contains(&n) && steam.insert(n) {
+                    queue.push_back(n);
+                }
             }
         }
+
+        Droplet {
+            cubes,
+            steam,
+            min,
+            max,
+        }
     }
 
-    // find air bubbles in the lava droplet
-    for x in x_min..=x_max {
-        for y in y_min..=y_max {
-            for z in z_min..=z_max {
-                let cube = (x, y, z);
-                if cubes.get(&cube).is_none() && steam.get(&cube).is_none() {
-                    air.insert(cube);
+    fn surface_area(&self) -> usize {
+        surface_area(&self.cubes)
+    }
+
+    // faces reachable by the steam, leaving out the walls of trapped air pockets
+    fn exterior_surface_area(&self) -> usize {
+        self.cubes
+            .iter()
+            .map(|k| {
+                neighbours(k)
+                    .iter()
+                    .filter(|&p| self.steam.contains(p))
+                    .count()
+            })
+            .sum::<usize>()
+    }
+
+    // air trapped inside the droplet, one set per pocket, largest first
+    fn pockets(&self) -> Vec<HashSet<Cube>> {
+        let ((x_min, y_min, z_min), (x_max, y_max, z_max)) = (self.min, self.max);
+        let mut air = HashSet::new();
+        for x in x_min..=x_max {
+            for y in y_min..=y_max {
+                for z in z_min..=z_max {
+                    let cube = (x, y, z);
+                    if !self.cubes.contains(&cube) && !self.steam.contains(&cube) {
+                        air.insert(cube);
+                    }
                 }
             }
         }
+        let mut pockets = components(&air);
+        pockets.sort_by_key(|pocket| std::cmp::Reverse(pocket.len()));
+        pockets
     }
 
-    // find cube surfaces that are next to air bubbles
-    let result = cubes
-        .iter()
-        .map(|k| {
-            neighbours(k)
-                .iter()
-                .filter(|&p| air.get(p).is_some())
-                .count()
-        })
-        .sum::<usize>();
+    fn enclosed_volume(&self) -> usize {
+        self.pockets().iter().map(|pocket| pocket.len()).sum()
+    }
+
+    // lava faces touching any of `cells`, in a stable order
+    fn faces_towards(&self, cells: &HashSet<Cube>) -> Vec<Face> {
+        let mut cubes = self.cubes.iter().collect::<Vec<_>>();
+        cubes.sort();
+        cubes
+            .into_iter()
+            .flat_map(|cube| {
+                neighbours(cube)
+                    .into_iter()
+                    .filter(|n| cells.contains(n))
+                    .map(move |n| Face::between(cube, &n))
+            })
+            .collect()
+    }
+
+    fn exterior_faces(&self) -> Vec<Face> {
+        self.faces_towards(&self.steam)
+    }
 
-    surface_area(&cubes) - result
+    // walls of the trapped air pockets, facing into the pockets
+    fn pocket_faces(&self) -> Vec<Face> {
+        let air = self.pockets().into_iter().flatten().collect();
+        self.faces_towards(&air)
+    }
+
+    // wavefront obj mesh of the exterior faces, optionally with the pocket walls as a second
+    // group, corners are shared between faces so the mesh is watertight
+    fn to_obj(&self, pockets: bool) -> String {
+        let mut groups = vec![("exterior", self.exterior_faces())];
+        if pockets {
+            groups.push(("pockets", self.pocket_faces()));
+        }
+
+        let mut vertices: HashMap<Cube, usize> = HashMap::new();
+        let mut order = vec![];
+        let mut faces = String::new();
+        for (name, group) in groups.iter() {
+            writeln!(faces, "g {}", name).unwrap();
+            for face in group {
+                let indices = face.corners.map(|corner| {
+                    *vertices.entry(corner).or_insert_with(|| {
+                        order.push(corner);
+                        order.len()
+                    })
+                });
+                writeln!(
+                    faces,
+                    "f {} {} {} {}",
+                    indices[0], indices[1], indices[2], indices[3]
+                )
+                .unwrap();
+            }
+        }
+
+        let mut obj = String::from("o droplet\n");
+        for (x, y, z) in order {
+            writeln!(obj, "v {} {} {}", x, y, z).unwrap();
+        }
+        obj + &faces
+    }
+
+    // ascii stl mesh, every face split into two triangles, pocket walls go into a second solid
+    fn to_stl(&self, pockets: bool) -> String {
+        let mut solids = vec![("exterior", self.exterior_faces())];
+        if pockets {
+            solids.push(("pockets", self.pocket_faces()));
+        }
+
+        let mut stl = String::new();
+        for (name, faces) in solids.iter() {
+            writeln!(stl, "solid {}", name).unwrap();
+            for face in faces {
+                let (nx, ny, nz) = face.normal;
+                for triangle in [[0, 1, 2], [0, 2, 3]] {
+                    writeln!(stl, "  facet normal {} {} {}", nx, ny, nz).unwrap();
+                    writeln!(stl, "    outer loop").unwrap();
+                    for corner in triangle {
+                        let (x, y, z) = face.corners[corner];
+                        writeln!(stl, "      vertex {} {} {}", x, y, z).unwrap();
+                    }
+                    writeln!(stl, "    endloop").unwrap();
+                    writeln!(stl, "  endfacet").unwrap();
+                }
+            }
+            writeln!(stl, "endsolid {}", name).unwrap();
+        }
+        stl
+    }
+
+    // amount of lava pieces that do not share a face with each other
+    fn pieces(&self) -> usize {
+        components(&self.cubes).len()
+    }
+}
+
+fn day18b(path: &str) -> usize {
+    let content = fs::read_to_string(path).expect("file not found");
+    let (_, cubes) = parse(content.as_str()).expect("parsing failed");
+    Droplet::new(cubes).exterior_surface_area()
 }
 
 #[cfg(test)]
@@ -142,4 +329,121 @@ mod tests {
         let actual = day18b("./data/day18final.txt");
         assert_eq!(actual, 2558);
     }
+
+    #[test]
+    fn find_air_pockets() {
+        let content = fs::read_to_string("./data/day18.txt").expect("file not found");
+        let (_, cubes) = parse(content.as_str()).expect("parsing failed");
+        let droplet = Droplet::new(cubes);
+        assert_eq!(droplet.surface_area(), 64);
+        assert_eq!(droplet.exterior_surface_area(), 58);
+        assert_eq!(droplet.pockets(), vec![HashSet::from([(2, 2, 5)])]);
+        assert_eq!(droplet.enclosed_volume(), 1);
+        assert_eq!(droplet.pieces(), 6);
+    }
+
+    #[test]
+    fn find_pockets_and_pieces() {
+        // a hollow 5x3x3 box split by a wall into two pockets, next to a loose cube
+        let mut cubes = HashSet::new();
+        for x in 0..5 {
+            for y in 0..3 {
+                for z in 0..3 {
+                    if x % 2 == 0 || y != 1 || z != 1 {
+                        cubes.insert((x, y, z));
+                    }
+                }
+            }
+        }
+        cubes.insert((10, 10, 10));
+
+        let droplet = Droplet::new(cubes);
+        let pockets = droplet.pockets();
+        assert_eq!(pockets.len(), 2);
+        assert!(pockets.iter().all(|pocket| pocket.len() == 1));
+        assert_eq!(droplet.enclosed_volume(), 2);
+        assert_eq!(droplet.pieces(), 2);
+        assert_eq!(
+            droplet.exterior_surface_area(),
+            2 * (5 * 3 + 5 * 3 + 3 * 3) + 6
+        );
+        assert_eq!(droplet.surface_area(), droplet.exterior_surface_area() + 12);
+    }
+
+    // every edge of a closed, consistently oriented mesh is walked once in each direction
+    fn assert_watertight(faces: &[Face]) {
+        let mut edges: HashMap<(Cube, Cube), usize> = HashMap::new();
+        for face in faces {
+            for idx in 0..4 {
+                let edge = (face.corners[idx], face.corners[(idx + 1) % 4]);
+                *edges.entry(edge).or_default() += 1;
+            }
+        }
+        for (&(from, to), &count) in edges.iter() {
+            assert_eq!(count, 1);
+            assert_eq!(edges.get(&(to, from)), Some(&1));
+        }
+    }
+
+    #[test]
+    fn orient_faces_outwards() {
+        let face = Face::between(&(0, 0, 0), &(1, 0, 0));
+        assert_eq!(face.corners, [(1, 0, 0), (1, 1, 0), (1, 1, 1), (1, 0, 1)]);
+        for n in neighbours(&(0, 0, 0)) {
+            let face = Face::between(&(0, 0, 0), &n);
+            let [a, b, c, _] = face.corners;
+            let (u, v) = (
+                (b.0 - a.0, b.1 - a.1, b.2 - a.2),
+                (c.0 - a.0, c.1 - a.1, c.2 - a.2),
+            );
+            let cross = (
+                u.1 * v.2 - u.2 * v.1,
+                u.2 * v.0 - u.0 * v.2,
+                u.0 * v.1 - u.1 * v.0,
+            );
+            assert_eq!(cross, n);
+            assert_eq!(face.normal, n);
+        }
+    }
+
+    #[test]
+    fn export_meshes() {
+        let mut cubes = HashSet::new();
+        for x in 0..3 {
+            for y in 0..3 {
+                for z in 0..3 {
+                    if (x, y, z) != (1, 1, 1) {
+                        cubes.insert((x, y, z));
+                    }
+                }
+            }
+        }
+        let droplet = Droplet::new(cubes);
+        assert_eq!(droplet.exterior_faces().len(), 54);
+        assert_eq!(droplet.pocket_faces().len(), 6);
+        assert_watertight(&droplet.exterior_faces());
+        assert_watertight(&droplet.pocket_faces());
+
+        let obj = droplet.to_obj(false);
+        assert!(obj.starts_with("o droplet\nv "));
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 56);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 54);
+        assert!(!obj.contains("g pockets"));
+
+        let obj = droplet.to_obj(true);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 64);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 60);
+        assert!(obj.contains("g exterior\n") && obj.contains("g pockets\n"));
+
+        let stl = droplet.to_stl(true);
+        assert!(stl.starts_with("solid exterior\n"));
+        assert!(stl.ends_with("endsolid pockets\n"));
+        assert_eq!(stl.matches("facet normal").count(), 120);
+
+        let content = fs::read_to_string("./data/day18.txt").expect("file not found");
+        let (_, cubes) = parse(content.as_str()).expect("parsing failed");
+        let droplet = Droplet::new(cubes);
+        assert_eq!(droplet.exterior_faces().len(), 58);
+        assert_eq!(droplet.pocket_faces().len(), 6);
+    }
 }
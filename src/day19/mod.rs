@@ -1,254 +1,394 @@
 #![allow(dead_code)]
 
 use rayon::prelude::*;
-use std::{cmp, collections::{HashSet, HashMap}, fs, iter::once};
+use std::{
+    cmp,
+    collections::HashSet,
+    fmt::{self, Display, Formatter},
+    fs,
+};
 
 use nom::{
-    bytes::complete::tag, character::complete::newline, multi::separated_list1, sequence::tuple,
+    bytes::complete::tag,
+    character::complete::{alpha1, multispace0, multispace1},
+    multi::{many1, separated_list1},
+    sequence::{preceded, separated_pair, terminated, tuple},
     IResult,
 };
 
-#[derive(Debug, Clone)]
+// robots and resources per kind, indexed like `Blueprint::resources`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct State {
     minute: i32,
-    ore_robots: Vec<Robot>,
-    clay_robots: Vec<Robot>,
-    obsidian_robots: Vec<Robot>,
-    geode_robots: Vec<Robot>,
-    ores: i32,
-    clays: i32,
-    obsidians: i32,
-    geodes: i32,
-}
-
-impl ToString for State {
-    fn to_string(&self) -> String {
-        format!(
-            "{}{}{}{}{}{}{}{}{}",
-            self.minute,
-            self.ores,
-            self.clays,
-            self.obsidians,
-            self.geodes,
-            self.ore_robots.len(),
-            self.clay_robots.len(),
-            self.obsidian_robots.len(),
-            self.geode_robots.len(),
-        )
-    }
+    robots: Vec<i32>,
+    stock: Vec<i32>,
 }
 
-impl Default for State {
-    fn default() -> Self {
+impl State {
+    // a single robot collecting `resource` and nothing in stock
+    fn new(kinds: usize, resource: usize) -> Self {
+        let mut robots = vec![0; kinds];
+        robots[resource] = 1;
         State {
             minute: 0,
-            ore_robots: vec![Robot::Ore],
-            clay_robots: vec![],
-            obsidian_robots: vec![],
-            geode_robots: vec![],
-            ores: 0,
-            clays: 0,
-            obsidians: 0,
-            geodes: 0,
+            robots,
+            stock: vec![0; kinds],
         }
     }
-}
 
-impl State {
-    fn is_done(&self, end: i32) -> bool {
-        return self.minute == end;
+    // minutes to wait before the robot can be bought, `None` if the current robots never
+    // collect enough
+    fn wait_for(&self, cost: &[i32]) -> Option<i32> {
+        cost.iter()
+            .enumerate()
+            .map(|(resource, amount)| {
+                let missing = amount - self.stock[resource];
+                match (missing <= 0, self.robots[resource] > 0) {
+                    (true, _) => Some(0),
+                    (false, true) => {
+                        Some((missing + self.robots[resource] - 1) / self.robots[resource])
+                    }
+                    (false, false) => None,
+                }
+            })
+            .try_fold(0, |acc, wait| wait.map(|wait| cmp::max(acc, wait)))
     }
 
-    fn harvest(&mut self) {
-        self.minute += 1;
+    // skip ahead until the robot is bought and ready
+    fn build(&self, robot: usize, cost: &[i32], wait: i32) -> State {
+        let mut next = self.clone();
+        next.minute += wait + 1;
+        for (resource, amount) in cost.iter().enumerate() {
+            next.stock[resource] += self.robots[resource] * (wait + 1) - amount;
+        }
+        next.robots[robot] += 1;
+        next
+    }
 
-        self.ores += self.ore_robots.len() as i32;
-        self.clays += self.clay_robots.len() as i32;
-        self.obsidians += self.obsidian_robots.len() as i32;
-        self.geodes += self.geode_robots.len() as i32;
+    // amount of `target` at the end if nothing else gets built
+    fn idle(&self, target: usize, minutes: i32) -> i32 {
+        self.stock[target] + self.robots[target] * (minutes - self.minute)
     }
 
-    fn try_buy(&self, blueprint: &Blueprint) -> Vec<Option<Robot>> {
-        blueprint
-            .costs
-            .iter()
-            .filter_map(|cost| match cost {
-                Cost::Ore(ore) => {
-                    if self.ores >= *ore && self.ore_robots.len() < 5 {
-                        Some(Robot::Ore)
-                    } else {
-                        None
-                    }
-                }
-                Cost::Clay(ore) => {
-                    if self.ores >= *ore && self.clay_robots.len() < 10 {
-                        Some(Robot::Clay)
-                    } else {
-                        None
-                    }
-                }
-                Cost::Obsidian(ore, clay) => {
-                    if self.ores >= *ore && self.clays >= *clay {
-                        Some(Robot::Obsidian)
-                    } else {
-                        None
+    // amount of `target` at the end if every robot kind paid from its own copy of the stock,
+    // so each kind gets built as soon as it could be, never less than what is actually possible
+    fn upper_bound(&self, costs: &[Option<Vec<i32>>], target: usize, minutes: i32) -> i32 {
+        let mut robots = self.robots.clone();
+        let mut pools = vec![self.stock.clone(); costs.len()];
+        let mut collected = self.stock[target];
+        for _ in self.minute..minutes {
+            let built = costs
+                .iter()
+                .zip(pools.iter_mut())
+                .map(|(cost, pool)| match cost {
+                    Some(cost) if cost.iter().zip(pool.iter()).all(|(c, p)| p >= c) => {
+                        pool.iter_mut().zip(cost).for_each(|(p, c)| *p -= c);
+                        true
                     }
-                }
-                Cost::Geode(ore, obsidian) => {
-                    if self.ores >= *ore && self.obsidians >= *obsidian {
-                        Some(Robot::Geode)
-                    } else {
-                        None
-                    }
-                }
-            })
-            .map(|robot| Some(robot))
-            .chain(once(None))
-            .collect::<Vec<_>>()
+                    _ => false,
+                })
+                .collect::<Vec<_>>();
+            for pool in pools.iter_mut() {
+                pool.iter_mut().zip(&robots).for_each(|(p, r)| *p += r);
+            }
+            collected += robots[target];
+            robots
+                .iter_mut()
+                .zip(built)
+                .for_each(|(robot, built)| *robot += built as i32);
+        }
+        collected
     }
+}
 
-    fn ready(&mut self, robot: &Robot, blueprint: &Blueprint) {
-        match robot {
-            Robot::Ore => {
-                blueprint
-                    .costs
-                    .iter()
-                    .filter_map(|cost| match cost {
-                        Cost::Ore(ore) => Some(ore),
-                        _ => None,
-                    })
-                    .for_each(|ore| self.ores -= ore);
-                    self.ore_robots.push(robot.clone());
-            }
-            Robot::Clay => {
-                blueprint
-                    .costs
-                    .iter()
-                    .filter_map(|cost| match cost {
-                        Cost::Clay(ore) => Some(ore),
-                        _ => None,
-                    })
-                    .for_each(|ore| self.ores -= ore);
-                    self.clay_robots.push(robot.clone());
-            }
-            Robot::Obsidian => {
-                blueprint
-                    .costs
-                    .iter()
-                    .filter_map(|cost| match cost {
-                        Cost::Obsidian(ore, clay) => Some((ore, clay)),
-                        _ => None,
-                    })
-                    .for_each(|(ore, clay)| {
-                        self.ores -= ore;
-                        self.clays -= clay;
-                    });
-                    self.obsidian_robots.push(robot.clone());
+// a robot name and what it costs, as (amount, resource) pairs
+type Recipe<'a> = (&'a str, Vec<(i32, &'a str)>);
+
+#[derive(Debug)]
+struct Blueprint {
+    id: i32,
+    // resource names in order of first appearance, robots are named after what they collect
+    resources: Vec<String>,
+    // what each robot costs, indexed by robot and then resource, `None` if it has no recipe
+    costs: Vec<Option<Vec<i32>>>,
+}
+
+impl Blueprint {
+    // build the cost matrix from the recipes
+    fn new(id: i32, recipes: Vec<Recipe<'_>>) -> Self {
+        let mut resources: Vec<String> = vec![];
+        let mut index = |name: &str| match resources.iter().position(|r| r == name) {
+            Some(idx) => idx,
+            None => {
+                resources.push(name.to_string());
+                resources.len() - 1
             }
-            Robot::Geode => {
-                blueprint
-                    .costs
-                    .iter()
-                    .filter_map(|cost| match cost {
-                        Cost::Geode(ore, obsidian) => Some((ore, obsidian)),
-                        _ => None,
-                    })
-                    .for_each(|(ore, obsidian)| {
-                        self.ores -= ore;
-                        self.obsidians -= obsidian;
-                    });
-                    self.geode_robots.push(robot.clone());
+        };
+        let recipes = recipes
+            .into_iter()
+            .map(|(robot, cost)| {
+                let robot = index(robot);
+                let cost = cost
+                    .into_iter()
+                    .map(|(amount, resource)| (index(resource), amount))
+                    .collect::<Vec<_>>();
+                (robot, cost)
+            })
+            .collect::<Vec<_>>();
+
+        let mut costs = vec![None; resources.len()];
+        for (robot, cost) in recipes {
+            let mut row = vec![0; resources.len()];
+            for (resource, amount) in cost {
+                row[resource] += amount;
             }
+            costs[robot] = Some(row);
+        }
+        Blueprint {
+            id,
+            resources,
+            costs,
         }
     }
+
+    fn resource(&self, name: &str) -> Option<usize> {
+        self.resources.iter().position(|r| r == name)
+    }
 }
 
-#[derive(Debug, Clone)]
-enum Robot {
-    Ore,
-    Clay,
-    Obsidian,
-    Geode,
+// the most of `target` the blueprint can collect within the given minutes, starting out with a
+// single robot of the first recipe's kind
+fn max_collected(blueprint: &Blueprint, target: usize, minutes: i32) -> i32 {
+    best_plan(blueprint, target, minutes).0
 }
 
-#[derive(Debug)]
-enum Cost {
-    Ore(i32),
-    Clay(i32),
-    Obsidian(i32, i32),
-    Geode(i32, i32),
+// like `max_collected`, along with the robots to build as (minute, robot) pairs, minutes
+// counted from 1 like in the puzzle
+fn best_plan(blueprint: &Blueprint, target: usize, minutes: i32) -> (i32, Vec<(i32, usize)>) {
+    let kinds = blueprint.resources.len();
+    // a robot only helps until it collects as much as can be spent in a single minute
+    let caps = (0..kinds)
+        .map(|resource| match resource == target {
+            true => i32::MAX,
+            false => blueprint
+                .costs
+                .iter()
+                .flatten()
+                .map(|cost| cost[resource])
+                .max()
+                .unwrap_or(0),
+        })
+        .collect::<Vec<_>>();
+
+    let mut best = 0;
+    let mut plan = vec![];
+    let mut visited = HashSet::new();
+    let mut queue = vec![(State::new(kinds, 0), vec![])];
+    while let Some((state, builds)) = queue.pop() {
+        if !visited.insert(state.clone()) {
+            continue;
+        }
+        if state.idle(target, minutes) > best {
+            best = state.idle(target, minutes);
+            plan = builds.clone();
+        }
+        if state.upper_bound(&blueprint.costs, target, minutes) <= best {
+            continue;
+        }
+
+        for (robot, cost) in blueprint.costs.iter().enumerate() {
+            let Some(cost) = cost else { continue };
+            if state.robots[robot] >= caps[robot] {
+                continue;
+            }
+            // a robot finished in the last minute never collects anything
+            match state.wait_for(cost) {
+                Some(wait) if state.minute + wait + 1 < minutes => {
+                    let mut builds = builds.clone();
+                    builds.push((state.minute + wait + 1, robot));
+                    queue.push((state.build(robot, cost, wait), builds));
+                }
+                _ => {}
+            }
+        }
+    }
+    (best, plan)
 }
 
-#[derive(Debug)]
-struct Blueprint {
-    id: i32,
-    costs: Vec<Cost>,
+// the most geodes the blueprint can crack within the given minutes
+fn max_geodes(blueprint: &Blueprint, minutes: i32) -> i32 {
+    match blueprint.resource("geode") {
+        Some(geode) => max_collected(blueprint, geode, minutes),
+        None => 0,
+    }
 }
 
-fn parse_id(input: &str) -> IResult<&str, i32> {
-    let (input, (_, id, _)) =
-        tuple((tag("Blueprint "), nom::character::complete::i32, tag(": ")))(input)?;
-    Ok((input, id))
+// the best way to crack geodes with a blueprint, replayed minute by minute
+struct Report<'a> {
+    blueprint: &'a Blueprint,
+    minutes: i32,
+    geodes: i32,
+    builds: Vec<(i32, usize)>,
+    // robots and stock at the end of every minute
+    states: Vec<State>,
 }
 
-fn parse_ore(input: &str) -> IResult<&str, Cost> {
-    let (input, (_, ore, _)) = tuple((
-        tag("Each ore robot costs "),
-        nom::character::complete::i32,
-        tag(" ore. "),
-    ))(input)?;
-    Ok((input, Cost::Ore(ore)))
+fn report(blueprint: &Blueprint, minutes: i32) -> Report<'_> {
+    let kinds = blueprint.resources.len();
+    let (geodes, builds) = match blueprint.resource("geode") {
+        Some(geode) => best_plan(blueprint, geode, minutes),
+        None => (0, vec![]),
+    };
+
+    let mut state = State::new(kinds, 0);
+    let mut states = vec![];
+    for minute in 1..=minutes {
+        let built = builds.iter().find(|&&(at, _)| at == minute);
+        let mut next = state.clone();
+        next.minute = minute;
+        if let Some(&(_, robot)) = built {
+            let cost = blueprint.costs[robot].as_ref().unwrap();
+            next.stock.iter_mut().zip(cost).for_each(|(s, c)| *s -= c);
+            next.robots[robot] += 1;
+        }
+        next.stock
+            .iter_mut()
+            .zip(&state.robots)
+            .for_each(|(s, r)| *s += r);
+        states.push(next.clone());
+        state = next;
+    }
+
+    Report {
+        blueprint,
+        minutes,
+        geodes,
+        builds,
+        states,
+    }
 }
 
-fn parse_clay(input: &str) -> IResult<&str, Cost> {
-    let (input, (_, clay, _)) = tuple((
-        tag("Each clay robot costs "),
-        nom::character::complete::i32,
-        tag(" ore. "),
-    ))(input)?;
-    Ok((input, Cost::Clay(clay)))
+impl Report<'_> {
+    fn robot(&self, resource: usize) -> String {
+        match self.blueprint.resources[resource].as_str() {
+            "geode" => "geode-cracking robot".to_string(),
+            name => format!("{}-collecting robot", name),
+        }
+    }
+
+    // "1 ore-collecting robot collects 1 ore; you now have 4 ore."
+    fn collected(&self, resource: usize, robots: i32, stock: i32) -> String {
+        let name = self.blueprint.resources[resource].as_str();
+        let plural = |n: i32| if n == 1 { "" } else { "s" };
+        match name {
+            "geode" => format!(
+                "{} {}{} crack{} {} geode{}; you now have {} open geode{}.",
+                robots,
+                self.robot(resource),
+                plural(robots),
+                if robots == 1 { "s" } else { "" },
+                robots,
+                plural(robots),
+                stock,
+                plural(stock)
+            ),
+            _ => format!(
+                "{} {}{} collect{} {} {}; you now have {} {}.",
+                robots,
+                self.robot(resource),
+                plural(robots),
+                if robots == 1 { "s" } else { "" },
+                robots,
+                name,
+                stock,
+                name
+            ),
+        }
+    }
 }
 
-fn parse_obsidian(input: &str) -> IResult<&str, Cost> {
-    let (input, (_, ore, _, clay, _)) = tuple((
-        tag("Each obsidian robot costs "),
-        nom::character::complete::i32,
-        tag(" ore and "),
-        nom::character::complete::i32,
-        tag(" clay. "),
-    ))(input)?;
-    Ok((input, Cost::Obsidian(ore, clay)))
+impl Display for Report<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Blueprint {}: {} geodes in {} minutes",
+            self.blueprint.id, self.geodes, self.minutes
+        )?;
+        let mut before = &State::new(self.blueprint.resources.len(), 0);
+        for (state, minute) in self.states.iter().zip(1..) {
+            writeln!(f, "\n== Minute {} ==", minute)?;
+            let built = self.builds.iter().find(|&&(at, _)| at == minute);
+            if let Some(&(_, robot)) = built {
+                let cost = self.blueprint.costs[robot].as_ref().unwrap();
+                let spent = cost
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &amount)| amount > 0)
+                    .map(|(resource, amount)| {
+                        format!("{} {}", amount, self.blueprint.resources[resource])
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" and ");
+                let robot = self.robot(robot);
+                let article = if robot.starts_with(['a', 'e', 'i', 'o', 'u']) {
+                    "an"
+                } else {
+                    "a"
+                };
+                writeln!(
+                    f,
+                    "Spend {} to start building {} {}.",
+                    spent, article, robot
+                )?;
+            }
+            for (resource, &robots) in before.robots.iter().enumerate() {
+                if robots > 0 {
+                    writeln!(
+                        f,
+                        "{}",
+                        self.collected(resource, robots, state.stock[resource])
+                    )?;
+                }
+            }
+            if let Some(&(_, robot)) = built {
+                writeln!(
+                    f,
+                    "The new {} is ready; you now have {} of them.",
+                    self.robot(robot),
+                    state.robots[robot]
+                )?;
+            }
+            before = state;
+        }
+        Ok(())
+    }
 }
 
-fn parse_geode(input: &str) -> IResult<&str, Cost> {
-    let (input, (_, ore, _, obsidian, _)) = tuple((
-        tag("Each geode robot costs "),
-        nom::character::complete::i32,
-        tag(" ore and "),
-        nom::character::complete::i32,
-        tag(" obsidian."),
+// "Each X robot costs 3 ore and 14 clay."
+fn parse_recipe(input: &str) -> IResult<&str, Recipe<'_>> {
+    let (input, (_, robot, _, cost, _)) = tuple((
+        tag("Each "),
+        alpha1,
+        tag(" robot costs "),
+        separated_list1(
+            tag(" and "),
+            separated_pair(nom::character::complete::i32, tag(" "), alpha1),
+        ),
+        tag("."),
     ))(input)?;
-    Ok((input, Cost::Geode(ore, obsidian)))
+    Ok((input, (robot, cost)))
 }
 
 fn parse_blueprint(input: &str) -> IResult<&str, Blueprint> {
-    let (input, id) = parse_id(input)?;
-    let (input, ore) = parse_ore(input)?;
-    let (input, clay) = parse_clay(input)?;
-    let (input, obsidian) = parse_obsidian(input)?;
-    let (input, geode) = parse_geode(input)?;
-
-    Ok((
-        input,
-        Blueprint {
-            id,
-            costs: vec![ore, clay, obsidian, geode],
-        },
-    ))
+    let (input, (_, id, _)) =
+        tuple((tag("Blueprint "), nom::character::complete::i32, tag(":")))(input)?;
+    let (input, recipes) = many1(preceded(multispace1, parse_recipe))(input)?;
+    Ok((input, Blueprint::new(id, recipes)))
 }
 
 fn parse(input: &str) -> IResult<&str, Vec<Blueprint>> {
-    let (input, blueprints) = separated_list1(newline, parse_blueprint)(input)?;
+    let (input, blueprints) =
+        terminated(separated_list1(multispace1, parse_blueprint), multispace0)(input)?;
     Ok((input, blueprints))
 }
 
@@ -256,90 +396,33 @@ fn day19a(path: &str, minutes: i32) -> i32 {
     let content = fs::read_to_string(path).expect("file not found");
     let (_, blueprints) = parse(&content).unwrap();
 
-    blueprints.par_iter().map(|blueprint| {
-        let state = State::default();
-        let mut queue = vec![state];
-        let mut best = 0;
-        let mut visited: HashSet<String> = HashSet::new();
-
-        while !queue.is_empty() {
-            let mut state = queue.pop().unwrap();
-            let key = state.to_string();
-            if visited.contains(&key) {
-                continue;
-            } else {
-                visited.insert(key);
-            }
-
-            let robots = state.try_buy(blueprint);
-            state.harvest();
-
-            if state.is_done(minutes) {
-                best = cmp::max(best, state.geodes);
-                continue;
-            }
-
-            for robot in robots.iter() {
-                match robot {
-                    None => queue.push(state.clone()),
-                    Some(robot) => {
-                        let mut new_state = state.clone();
-                        new_state.ready(robot, blueprint);
-                        queue.push(new_state);
-                    }
-                }
-            }
-        }
-        best * blueprint.id
-    }).sum()
+    blueprints
+        .par_iter()
+        .map(|blueprint| max_geodes(blueprint, minutes) * blueprint.id)
+        .sum()
 }
 
 fn day19b(path: &str, minutes: i32) -> i32 {
     let content = fs::read_to_string(path).expect("file not found");
     let (_, blueprints) = parse(&content).unwrap();
-    let first_three = blueprints.iter().take(3).collect::<Vec<_>>();
-
-    first_three.par_iter().map(|blueprint| {
-        let state = State::default();
-        let mut queue = vec![state];
-        let mut visited: HashSet<String> = HashSet::new();
-        let mut scores: HashMap<i32, i32> = HashMap::new();
-
-        while !queue.is_empty() {
-            let mut state = queue.pop().unwrap();
-            let key = state.to_string();
-            if visited.contains(&key) {
-                continue;
-            } else {
-                visited.insert(key);
-            }
-
-            let robots = state.try_buy(blueprint);
-            state.harvest();
 
-            if state.minute > 24 && scores.contains_key(&state.minute) && scores.get(&state.minute).unwrap() > &state.geodes {
-                continue;
-            } else {
-                scores.insert(state.minute, state.geodes);
-            }
+    blueprints
+        .par_iter()
+        .take(3)
+        .map(|blueprint| max_geodes(blueprint, minutes))
+        .product()
+}
 
-            if state.is_done(minutes) {
-                continue;
-            }
+// one report per blueprint, separated by blank lines
+fn day19_report(path: &str, minutes: i32) -> String {
+    let content = fs::read_to_string(path).expect("file not found");
+    let (_, blueprints) = parse(&content).unwrap();
 
-            for robot in robots.iter() {
-                match robot {
-                    None => queue.push(state.clone()),
-                    Some(robot) => {
-                        let mut new_state = state.clone();
-                        new_state.ready(robot, blueprint);
-                        queue.push(new_state);
-                    }
-                }
-            }
-        }
-        scores.get(&minutes).unwrap().clone()
-    }).product()
+    blueprints
+        .par_iter()
+        .map(|blueprint| report(blueprint, minutes).to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[cfg(test)]
@@ -348,30 +431,143 @@ mod tests {
     use super::*;
 
     #[test]
-    #[ignore]
     fn find_quality_level() {
         let actual = day19a("./data/day19.txt", 24);
         assert_eq!(actual, 33);
     }
 
     #[test]
-    #[ignore]
     fn find_quality_level_first_three() {
         let actual = day19b("./data/day19.txt", 32);
         assert_eq!(actual, 56 * 62);
     }
 
     #[test]
-    #[ignore]
     fn find_quality_level_part_a() {
         let actual = day19a("./data/day19final.txt", 24);
         assert_eq!(actual, 1023);
     }
 
     #[test]
-    #[ignore]
     fn find_quality_level_part_b() {
         let actual = day19b("./data/day19final.txt", 32);
         assert_eq!(actual, 13520);
     }
+
+    #[test]
+    fn find_max_geodes() {
+        let content = fs::read_to_string("./data/day19.txt").expect("file not found");
+        let (_, blueprints) = parse(&content).unwrap();
+        assert_eq!(max_geodes(&blueprints[0], 24), 9);
+        assert_eq!(max_geodes(&blueprints[1], 24), 12);
+        assert_eq!(max_geodes(&blueprints[0], 32), 56);
+        assert_eq!(max_geodes(&blueprints[0], 0), 0);
+    }
+
+    // every possible build order, without any pruning
+    fn exhaustive(blueprint: &Blueprint, state: State, target: usize, minutes: i32) -> i32 {
+        let mut best = state.idle(target, minutes);
+        for (robot, cost) in blueprint.costs.iter().enumerate() {
+            if let Some(cost) = cost {
+                if let Some(wait) = state.wait_for(cost) {
+                    if state.minute + wait + 1 < minutes {
+                        let next = state.build(robot, cost, wait);
+                        best = cmp::max(best, exhaustive(blueprint, next, target, minutes));
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn parse_generic_blueprints() {
+        let input = "Blueprint 7:
+  Each sand robot costs 2 sand.
+  Each glass robot costs 3 sand and 1 fire.
+  Each fire robot costs 4 sand.
+  Each lens robot costs 2 glass and 2 fire.
+
+Blueprint 8: Each sand robot costs 1 sand. Each gold robot costs 1 dust.
+";
+        let (rest, blueprints) = parse(input).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(blueprints.len(), 2);
+
+        let blueprint = &blueprints[0];
+        assert_eq!(blueprint.id, 7);
+        assert_eq!(blueprint.resources, vec!["sand", "glass", "fire", "lens"]);
+        assert_eq!(blueprint.costs[1], Some(vec![3, 0, 1, 0]));
+        assert_eq!(blueprint.costs[3], Some(vec![0, 2, 2, 0]));
+        assert_eq!(max_geodes(blueprint, 24), 0);
+
+        let lens = blueprint.resource("lens").unwrap();
+        for minutes in [10, 14, 16] {
+            let expected = exhaustive(blueprint, State::new(4, 0), lens, minutes);
+            assert_eq!(max_collected(blueprint, lens, minutes), expected);
+        }
+
+        // dust has no robot, so gold can never be collected
+        let blueprint = &blueprints[1];
+        assert_eq!(blueprint.resources, vec!["sand", "gold", "dust"]);
+        assert_eq!(blueprint.costs[2], None);
+        assert_eq!(max_collected(blueprint, 1, 24), 0);
+    }
+
+    #[test]
+    fn match_exhaustive_search() {
+        let content = fs::read_to_string("./data/day19.txt").expect("file not found");
+        let (_, blueprints) = parse(&content).unwrap();
+        for blueprint in blueprints.iter() {
+            let geode = blueprint.resource("geode").unwrap();
+            let clay = blueprint.resource("clay").unwrap();
+            assert_eq!(
+                blueprint.resources,
+                vec!["ore", "clay", "obsidian", "geode"]
+            );
+            assert_eq!(
+                max_collected(blueprint, clay, 12),
+                exhaustive(blueprint, State::new(4, 0), clay, 12)
+            );
+            assert_eq!(
+                max_collected(blueprint, geode, 20),
+                exhaustive(blueprint, State::new(4, 0), geode, 20)
+            );
+        }
+    }
+
+    #[test]
+    fn report_build_order() {
+        let content = fs::read_to_string("./data/day19.txt").expect("file not found");
+        let (_, blueprints) = parse(&content).expect("parsing failed");
+        let report = report(&blueprints[0], 24);
+        let text = report.to_string();
+        let lines = text.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], "Blueprint 1: 9 geodes in 24 minutes");
+        assert_eq!(
+            lines[2..4],
+            [
+                "== Minute 1 ==",
+                "1 ore-collecting robot collects 1 ore; you now have 1 ore."
+            ]
+        );
+        assert_eq!(
+            lines.last(),
+            Some(&"2 geode-cracking robots crack 2 geodes; you now have 9 open geodes.")
+        );
+        assert_eq!(report.states.len(), 24);
+        assert_eq!(report.states[23].stock[3], 9);
+        assert!(report
+            .states
+            .iter()
+            .all(|s| s.stock.iter().all(|&n| n >= 0)));
+    }
+
+    #[test]
+    fn report_every_blueprint() {
+        let text = day19_report("./data/day19.txt", 24);
+        assert!(text.contains("Blueprint 1: 9 geodes in 24 minutes"));
+        assert!(text.contains("Blueprint 2: 12 geodes in 24 minutes"));
+    }
 }
@@ -2,6 +2,8 @@
 
 use std::fs;
 
+use itertools::Itertools;
+
 use nom::{
     character::complete::{self, newline},
     multi::separated_list1,
@@ -13,47 +15,255 @@ fn parse(input: &str) -> IResult<&str, Vec<i64>> {
     Ok((input, numbers))
 }
 
-fn day20a(path: &str) -> i64 {
-    let content = fs::read_to_string(path).expect("file not found");
-    let (_, numbers) = parse(&content).unwrap();
+const NIL: usize = usize::MAX;
+
+// implicit treap over the original indexes: the in-order sequence is the current
+// arrangement, and parent links let a node find its own position in O(log n)
+struct Treap {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    priority: Vec<u64>,
+    root: usize,
+}
 
-    let mut indexes = (0..numbers.len()).collect::<Vec<_>>();
-    for (idx, &num) in numbers.iter().enumerate() {
-        let pos = indexes.iter().position(|&i| i == idx).unwrap();
-        indexes.remove(pos);
-        let new_idx = (pos as i64 + num).rem_euclid(indexes.len() as i64) as usize;
-        indexes.insert(new_idx, idx);
+impl Treap {
+    fn new(len: usize) -> Self {
+        // xorshift, so the shape is the same on every run
+        let mut seed = 0x9e37_79b9_7f4a_7c15_u64;
+        let priority = (0..len)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                seed
+            })
+            .collect();
+        let mut treap = Treap {
+            left: vec![NIL; len],
+            right: vec![NIL; len],
+            parent: vec![NIL; len],
+            size: vec![1; len],
+            priority,
+            root: NIL,
+        };
+        for node in 0..len {
+            treap.root = treap.merge(treap.root, node);
+        }
+        treap
     }
 
-    let zero_idx = numbers.iter().position(|&i| i == 0).unwrap();
-    let zero = indexes.iter().position(|&i| i == zero_idx).unwrap();
-    [1000, 2000, 3000]
-        .iter()
-        .map(|i| numbers[indexes[(zero + i) % indexes.len()]])
-        .sum()
+    fn len(&self) -> usize {
+        self.size_of(self.root)
+    }
+
+    fn size_of(&self, node: usize) -> usize {
+        if node == NIL {
+            0
+        } else {
+            self.size[node]
+        }
+    }
+
+    fn update(&mut self, node: usize) {
+        let (left, right) = (self.left[node], self.right[node]);
+        self.size[node] = 1 + self.size_of(left) + self.size_of(right);
+        if left != NIL {
+            self.parent[left] = node;
+        }
+        if right != NIL {
+            self.parent[right] = node;
+        }
+    }
+
+    // all nodes of a come before all nodes of b
+    fn merge(&mut self, a: usize, b: usize) -> usize {
+        if a == NIL || b == NIL {
+            let root = if a == NIL { b } else { a };
+            if root != NIL {
+                self.parent[root] = NIL;
+            }
+            return root;
+        }
+        if self.priority[a] > self.priority[b] {
+            self.right[a] = self.merge(self.right[a], b);
+            self.update(a);
+            self.parent[a] = NIL;
+            a
+        } else {
+            self.left[b] = self.merge(a, self.left[b]);
+            self.update(b);
+            self.parent[b] = NIL;
+            b
+        }
+    }
+
+    // the first k nodes go left, the rest right
+    fn split(&mut self, node: usize, k: usize) -> (usize, usize) {
+        if node == NIL {
+            return (NIL, NIL);
+        }
+        self.parent[node] = NIL;
+        let left_size = self.size_of(self.left[node]);
+        if k <= left_size {
+            let (a, b) = self.split(self.left[node], k);
+            self.left[node] = b;
+            self.update(node);
+            (a, node)
+        } else {
+            let (a, b) = self.split(self.right[node], k - left_size - 1);
+            self.right[node] = a;
+            self.update(node);
+            (node, b)
+        }
+    }
+
+    fn position(&self, mut node: usize) -> usize {
+        let mut pos = self.size_of(self.left[node]);
+        while self.parent[node] != NIL {
+            let parent = self.parent[node];
+            if self.right[parent] == node {
+                pos += self.size_of(self.left[parent]) + 1;
+            }
+            node = parent;
+        }
+        pos
+    }
+
+    // take node out and put it back so that it ends up at position to
+    fn move_to(&mut self, node: usize, to: usize) {
+        let pos = self.position(node);
+        let (before, rest) = self.split(self.root, pos);
+        let (_, after) = self.split(rest, 1);
+        self.root = self.merge(before, after);
+
+        self.left[node] = NIL;
+        self.right[node] = NIL;
+        self.update(node);
+        let (before, after) = self.split(self.root, to);
+        let before = self.merge(before, node);
+        self.root = self.merge(before, after);
+    }
+
+    fn in_order(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.len());
+        let mut stack = vec![];
+        let mut node = self.root;
+        while node != NIL || !stack.is_empty() {
+            while node != NIL {
+                stack.push(node);
+                node = self.left[node];
+            }
+            node = stack.pop().unwrap();
+            order.push(node);
+            node = self.right[node];
+        }
+        order
+    }
 }
 
-fn day20b(path: &str) -> i64 {
-    let content = fs::read_to_string(path).expect("file not found");
-    let (_, numbers) = parse(&content).unwrap();
-    let numbers = numbers.iter().map(|&i| i * 811589153).collect::<Vec<_>>();
+// the original indexes of the numbers, in the order mixing leaves them
+fn mix_order(numbers: &[i64], key: i64, rounds: usize) -> Vec<usize> {
+    if numbers.len() < 2 {
+        return (0..numbers.len()).collect();
+    }
 
-    let mut indexes = (0..numbers.len()).collect::<Vec<_>>();
-    for _ in 0..10 {
+    let mut treap = Treap::new(numbers.len());
+    let others = numbers.len() as i64 - 1;
+    for _ in 0..rounds {
         for (idx, &num) in numbers.iter().enumerate() {
-            let pos = indexes.iter().position(|&i| i == idx).unwrap();
-            indexes.remove(pos);
-            let new_idx = (pos as i64 + num).rem_euclid(indexes.len() as i64) as usize;
-            indexes.insert(new_idx, idx);
+            let pos = treap.position(idx) as i64;
+            let moved = (num % others) * (key % others);
+            treap.move_to(idx, (pos + moved).rem_euclid(others) as usize);
         }
     }
+    treap.in_order()
+}
 
-    let zero_idx = numbers.iter().position(|&i| i == 0).unwrap();
-    let zero = indexes.iter().position(|&i| i == zero_idx).unwrap();
-    [1000, 2000, 3000]
+// multiplies every number by key and mixes them rounds times, returning the final arrangement
+fn mix(numbers: &[i64], key: i64, rounds: usize) -> Result<Vec<i64>, GroveError> {
+    let decrypted = numbers
         .iter()
-        .map(|i| numbers[indexes[(zero + i) % indexes.len()]])
-        .sum()
+        .map(|i| i.checked_mul(key).ok_or(GroveError::Overflow))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(mix_order(numbers, key, rounds)
+        .into_iter()
+        .map(|i| decrypted[i])
+        .collect())
+}
+
+struct MixConfig {
+    key: i64,
+    rounds: usize,
+    // the value the offsets count from, after applying the key
+    anchor: i64,
+    offsets: Vec<usize>,
+}
+
+impl MixConfig {
+    // part one: mix once, without a key
+    fn plain() -> Self {
+        MixConfig {
+            key: 1,
+            rounds: 1,
+            anchor: 0,
+            offsets: vec![1000, 2000, 3000],
+        }
+    }
+
+    // part two: apply the decryption key and mix ten times
+    fn decrypting() -> Self {
+        MixConfig {
+            key: 811589153,
+            rounds: 10,
+            ..MixConfig::plain()
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum GroveError {
+    MissingAnchor(i64),
+    DuplicateAnchor(i64),
+    // a number times the key, or the sum of the coordinates, does not fit in an i64
+    Overflow,
+}
+
+// sum of the mixed numbers at each offset after the anchor, wrapping around
+fn grove_coordinates(numbers: &[i64], config: &MixConfig) -> Result<i64, GroveError> {
+    let mut anchors = numbers
+        .iter()
+        .positions(|&i| i.checked_mul(config.key) == Some(config.anchor));
+    let Some(anchor_idx) = anchors.next() else {
+        return Err(GroveError::MissingAnchor(config.anchor));
+    };
+    if anchors.next().is_some() {
+        return Err(GroveError::DuplicateAnchor(config.anchor));
+    }
+
+    let order = mix_order(numbers, config.key, config.rounds);
+    let anchor = order.iter().position(|&i| i == anchor_idx).unwrap();
+    config.offsets.iter().try_fold(0i64, |sum, i| {
+        numbers[order[(anchor + i) % order.len()]]
+            .checked_mul(config.key)
+            .and_then(|value| sum.checked_add(value))
+            .ok_or(GroveError::Overflow)
+    })
+}
+
+fn day20a(path: &str) -> i64 {
+    let content = fs::read_to_string(path).expect("file not found");
+    let (_, numbers) = parse(&content).unwrap();
+
+    grove_coordinates(&numbers, &MixConfig::plain()).expect("no single zero")
+}
+
+fn day20b(path: &str) -> i64 {
+    let content = fs::read_to_string(path).expect("file not found");
+    let (_, numbers) = parse(&content).unwrap();
+
+    grove_coordinates(&numbers, &MixConfig::decrypting()).expect("no single zero")
 }
 
 #[cfg(test)]
@@ -85,4 +295,142 @@ mod tests {
         // not correct
         assert_eq!(actual, 4090409331120);
     }
+
+    // the original O(n²) mixing, kept to check the treap against
+    fn mix_naive(numbers: &[i64], key: i64, rounds: usize) -> Vec<i64> {
+        let numbers = numbers.iter().map(|&i| i * key).collect::<Vec<_>>();
+        let mut indexes = (0..numbers.len()).collect::<Vec<_>>();
+        for _ in 0..rounds {
+            for (idx, &num) in numbers.iter().enumerate() {
+                let pos = indexes.iter().position(|&i| i == idx).unwrap();
+                indexes.remove(pos);
+                let new_idx = (pos as i64 + num).rem_euclid(indexes.len() as i64) as usize;
+                indexes.insert(new_idx, idx);
+            }
+        }
+        indexes.into_iter().map(|i| numbers[i]).collect()
+    }
+
+    #[test]
+    fn mix_example() {
+        let content = fs::read_to_string("./data/day20.txt").expect("file not found");
+        let (_, numbers) = parse(&content).expect("parsing failed");
+        let mixed = mix(&numbers, 1, 1).unwrap();
+        let zero = mixed.iter().position(|&i| i == 0).unwrap();
+        let mut from_zero = mixed.clone();
+        from_zero.rotate_left(zero);
+        assert_eq!(from_zero, vec![0, 3, -2, 1, 2, -3, 4]);
+    }
+
+    #[test]
+    fn mix_matches_naive() {
+        let content = fs::read_to_string("./data/day20final.txt").expect("file not found");
+        let (_, numbers) = parse(&content).expect("parsing failed");
+        let numbers = &numbers[..500];
+        assert_eq!(mix(numbers, 1, 1), Ok(mix_naive(numbers, 1, 1)));
+        assert_eq!(
+            mix(numbers, 811589153, 3),
+            Ok(mix_naive(numbers, 811589153, 3))
+        );
+    }
+
+    #[test]
+    fn mix_tiny_inputs() {
+        assert_eq!(mix(&[], 1, 1), Ok(vec![]));
+        assert_eq!(mix(&[5], 2, 3), Ok(vec![10]));
+        assert_eq!(mix(&[1, -1], 1, 1), Ok(mix_naive(&[1, -1], 1, 1)));
+    }
+
+    #[test]
+    fn grove_coordinates_with_config() {
+        let numbers = [1, 2, -3, 3, -2, 0, 4];
+        let config = MixConfig {
+            offsets: vec![1, 2, 3],
+            ..MixConfig::plain()
+        };
+        assert_eq!(grove_coordinates(&numbers, &config), Ok(3 - 2 + 1));
+
+        let config = MixConfig {
+            anchor: 4,
+            offsets: vec![1],
+            ..MixConfig::plain()
+        };
+        assert_eq!(grove_coordinates(&numbers, &config), Ok(0));
+
+        // a zero key turns every number into the anchor
+        let config = MixConfig {
+            key: 0,
+            ..MixConfig::decrypting()
+        };
+        assert_eq!(
+            grove_coordinates(&numbers, &config),
+            Err(GroveError::DuplicateAnchor(0))
+        );
+    }
+
+    #[test]
+    fn grove_coordinates_from_a_decrypted_anchor() {
+        let numbers = [1, 2, -3, 3, -2, 0, 4];
+        let config = MixConfig {
+            anchor: 4 * 811589153,
+            offsets: vec![1, 2, 3],
+            ..MixConfig::decrypting()
+        };
+        // after ten rounds the decrypted sequence runs 0, -2434767459, 1623178306, 3246356612,
+        // -1623178306, 2434767459, 811589153
+        assert_eq!(
+            grove_coordinates(&numbers, &config),
+            Ok(-1623178306 + 2434767459 + 811589153)
+        );
+        assert_eq!(
+            grove_coordinates(
+                &numbers,
+                &MixConfig {
+                    anchor: 4,
+                    ..MixConfig::decrypting()
+                }
+            ),
+            Err(GroveError::MissingAnchor(4))
+        );
+    }
+
+    #[test]
+    fn keys_that_overflow() {
+        let numbers = [1, 2, -3, 3, -2, 0, 4];
+        assert_eq!(mix(&numbers, i64::MAX / 2, 1), Err(GroveError::Overflow));
+
+        // mixed from zero the numbers run 0, 3, -2, 1, 2, -3, 4; decrypted 4 and 3 both
+        // fit, their sum does not
+        let config = MixConfig {
+            key: i64::MAX / 4,
+            offsets: vec![6, 1],
+            ..MixConfig::plain()
+        };
+        assert_eq!(
+            grove_coordinates(&numbers, &config),
+            Err(GroveError::Overflow)
+        );
+        // the decrypted 4 alone is too big
+        let config = MixConfig {
+            key: i64::MAX / 3 + 1,
+            offsets: vec![6],
+            ..MixConfig::plain()
+        };
+        assert_eq!(
+            grove_coordinates(&numbers, &config),
+            Err(GroveError::Overflow)
+        );
+    }
+
+    #[test]
+    fn grove_coordinates_need_a_single_anchor() {
+        assert_eq!(
+            grove_coordinates(&[1, 2, 3], &MixConfig::plain()),
+            Err(GroveError::MissingAnchor(0))
+        );
+        assert_eq!(
+            grove_coordinates(&[0, 2, 0], &MixConfig::decrypting()),
+            Err(GroveError::DuplicateAnchor(0))
+        );
+    }
 }
@@ -1,6 +1,9 @@
 #![allow(dead_code)]
 
-use std::{collections::HashMap, fs};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
 
 use nom::{
     branch::alt,
@@ -17,7 +20,6 @@ enum Riddle {
     Multiply(String, String),
     Divide(String, String),
     Subtract(String, String),
-    Equal(String, String),
 }
 
 fn parse_calculation(input: &str) -> IResult<&str, (String, Riddle)> {
@@ -61,46 +63,494 @@ fn parse(input: &str) -> IResult<&str, HashMap<String, Riddle>> {
     Ok((input, riddles))
 }
 
-fn traverse(riddles: &HashMap<String, Riddle>, job: &str) -> i64 {
-    match &riddles[job] {
-        Riddle::Num(num) => *num,
-        Riddle::Add(left, right) => traverse(riddles, &left) + traverse(riddles, &right),
-        Riddle::Multiply(left, right) => traverse(riddles, &left) * traverse(riddles, &right),
-        Riddle::Divide(left, right) => traverse(riddles, &left) / traverse(riddles, &right),
-        Riddle::Subtract(left, right) => traverse(riddles, &left) - traverse(riddles, &right),
-        Riddle::Equal(left, right) => {
-            let left = traverse(riddles, &left);
-            let right = traverse(riddles, &right);
-            if left > right {
-                -1
-            } else if left < right {
-                1
-            } else {
-                0
-            }
+#[derive(Debug, PartialEq)]
+enum ArithmeticError {
+    Overflow,
+    DivideByZero,
+    // a division with a remainder under a strict backend
+    Inexact,
+}
+
+// the number type riddles are evaluated in
+trait Numeric: Copy {
+    fn from_i64(num: i64) -> Self;
+    fn add(self, other: Self) -> Result<Self, ArithmeticError>;
+    fn subtract(self, other: Self) -> Result<Self, ArithmeticError>;
+    fn multiply(self, other: Self) -> Result<Self, ArithmeticError>;
+    fn divide(self, other: Self) -> Result<Self, ArithmeticError>;
+}
+
+// checked integers with truncating division, the way the monkeys do it
+impl Numeric for i64 {
+    fn from_i64(num: i64) -> Self {
+        num
+    }
+
+    fn add(self, other: Self) -> Result<Self, ArithmeticError> {
+        self.checked_add(other).ok_or(ArithmeticError::Overflow)
+    }
+
+    fn subtract(self, other: Self) -> Result<Self, ArithmeticError> {
+        self.checked_sub(other).ok_or(ArithmeticError::Overflow)
+    }
+
+    fn multiply(self, other: Self) -> Result<Self, ArithmeticError> {
+        self.checked_mul(other).ok_or(ArithmeticError::Overflow)
+    }
+
+    fn divide(self, other: Self) -> Result<Self, ArithmeticError> {
+        if other == 0 {
+            return Err(ArithmeticError::DivideByZero);
         }
+        self.checked_div(other).ok_or(ArithmeticError::Overflow)
     }
 }
 
-fn binary_search(
-    riddles: &mut HashMap<String, Riddle>,
-    job: &str,
-    mut lower: i64,
-    mut upper: i64,
-) -> i64 {
-    loop {
-        let mid = (lower + upper) / 2;
-        riddles.insert("humn".to_string(), Riddle::Num(mid));
-        let result = traverse(riddles, job);
-        if result == 0 {
-            return mid - 1;
+// checked integers that refuse to divide with a remainder
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Strict(i64);
+
+impl Numeric for Strict {
+    fn from_i64(num: i64) -> Self {
+        Strict(num)
+    }
+
+    fn add(self, other: Self) -> Result<Self, ArithmeticError> {
+        self.0.add(other.0).map(Strict)
+    }
+
+    fn subtract(self, other: Self) -> Result<Self, ArithmeticError> {
+        self.0.subtract(other.0).map(Strict)
+    }
+
+    fn multiply(self, other: Self) -> Result<Self, ArithmeticError> {
+        self.0.multiply(other.0).map(Strict)
+    }
+
+    fn divide(self, other: Self) -> Result<Self, ArithmeticError> {
+        let quotient = self.0.divide(other.0)?;
+        if self.0 % other.0 != 0 {
+            return Err(ArithmeticError::Inexact);
         }
-        if result == -1 {
-            lower = mid;
+        Ok(Strict(quotient))
+    }
+}
+
+// exact fractions, kept reduced with a positive denominator
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rational {
+    num: i128,
+    den: i128,
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl Rational {
+    fn new(num: i128, den: i128) -> Result<Self, ArithmeticError> {
+        if den == 0 {
+            return Err(ArithmeticError::DivideByZero);
+        }
+        let divisor = gcd(num, den) * den.signum();
+        Ok(Rational {
+            num: num.checked_div(divisor).ok_or(ArithmeticError::Overflow)?,
+            den: den.checked_div(divisor).ok_or(ArithmeticError::Overflow)?,
+        })
+    }
+
+    fn to_integer(self) -> Option<i64> {
+        if self.den == 1 {
+            self.num.try_into().ok()
         } else {
-            upper = mid;
+            None
+        }
+    }
+}
+
+impl Numeric for Rational {
+    fn from_i64(num: i64) -> Self {
+        Rational {
+            num: num as i128,
+            den: 1,
+        }
+    }
+
+    fn add(self, other: Self) -> Result<Self, ArithmeticError> {
+        let common = gcd(self.den, other.den);
+        let (left, right) = (self.den / common, other.den / common);
+        let num = self
+            .num
+            .checked_mul(right)
+            .zip(other.num.checked_mul(left))
+            .and_then(|(a, b)| a.checked_add(b));
+        let den = self.den.checked_mul(right);
+        match (num, den) {
+            (Some(num), Some(den)) => Rational::new(num, den),
+            _ => Err(ArithmeticError::Overflow),
         }
     }
+
+    fn subtract(self, other: Self) -> Result<Self, ArithmeticError> {
+        let negated = other.num.checked_neg().ok_or(ArithmeticError::Overflow)?;
+        self.add(Rational {
+            num: negated,
+            den: other.den,
+        })
+    }
+
+    fn multiply(self, other: Self) -> Result<Self, ArithmeticError> {
+        // cross-reduce first so the products stay small
+        let a = gcd(self.num, other.den).max(1);
+        let b = gcd(other.num, self.den).max(1);
+        let num = (self.num / a).checked_mul(other.num / b);
+        let den = (self.den / b).checked_mul(other.den / a);
+        match (num, den) {
+            (Some(num), Some(den)) => Rational::new(num, den),
+            _ => Err(ArithmeticError::Overflow),
+        }
+    }
+
+    fn divide(self, other: Self) -> Result<Self, ArithmeticError> {
+        if other.num == 0 {
+            return Err(ArithmeticError::DivideByZero);
+        }
+        self.multiply(Rational::new(other.den, other.num)?)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Problem {
+    // the monkey asked about does not exist
+    Missing(String),
+    // job waits on a monkey nobody defined
+    Undefined { job: String, name: String },
+    // every monkey waits on the next, and the last on the first
+    Cycle(Vec<String>),
+    Unreachable(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum EvalError {
+    Arithmetic { job: String, error: ArithmeticError },
+    Invalid(Problem),
+}
+
+fn operands(riddle: &Riddle) -> Option<(&str, &str)> {
+    match riddle {
+        Riddle::Num(_) => None,
+        Riddle::Add(left, right)
+        | Riddle::Multiply(left, right)
+        | Riddle::Divide(left, right)
+        | Riddle::Subtract(left, right) => Some((left, right)),
+    }
+}
+
+fn children(riddle: &Riddle) -> Vec<&str> {
+    operands(riddle).map_or(vec![], |(left, right)| vec![left, right])
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mark {
+    Open,
+    Done,
+}
+
+// depth first from start, appending every monkey after the ones it waits on; with somewhere
+// to collect problems, undefined monkeys and cycles are noted and the walk carries on
+// without them, otherwise the first one ends it
+fn visit<'a>(
+    riddles: &'a HashMap<String, Riddle>,
+    start: &'a str,
+    marks: &mut HashMap<&'a str, Mark>,
+    order: &mut Vec<&'a str>,
+    mut problems: Option<&mut Vec<Problem>>,
+) -> Result<(), Problem> {
+    if marks.contains_key(start) {
+        return Ok(());
+    }
+    let Some((start, _)) = riddles.get_key_value(start) else {
+        return Err(Problem::Missing(start.to_string()));
+    };
+    marks.insert(start, Mark::Open);
+    let mut stack = vec![(start.as_str(), 0)];
+    while let Some((job, next)) = stack.last_mut() {
+        let job = *job;
+        let Some(&child) = children(&riddles[job]).get(*next) else {
+            stack.pop();
+            marks.insert(job, Mark::Done);
+            order.push(job);
+            continue;
+        };
+        *next += 1;
+
+        let problem = match marks.get(child) {
+            Some(Mark::Done) => continue,
+            Some(Mark::Open) => {
+                let from = stack.iter().position(|&(job, _)| job == child).unwrap();
+                Problem::Cycle(
+                    stack[from..]
+                        .iter()
+                        .map(|(job, _)| job.to_string())
+                        .collect(),
+                )
+            }
+            None => match riddles.get_key_value(child) {
+                Some((child, _)) => {
+                    marks.insert(child, Mark::Open);
+                    stack.push((child, 0));
+                    continue;
+                }
+                None => Problem::Undefined {
+                    job: job.to_string(),
+                    name: child.to_string(),
+                },
+            },
+        };
+        if let Some(problems) = problems.as_deref_mut() {
+            problems.push(problem);
+            continue;
+        }
+        // close what is still open so a later walk does not trip over it
+        for (job, _) in stack {
+            marks.insert(job, Mark::Done);
+        }
+        return Err(problem);
+    }
+    Ok(())
+}
+
+// the monkeys root depends on, each after everything it waits on
+fn topological_order<'a>(
+    riddles: &'a HashMap<String, Riddle>,
+    root: &'a str,
+) -> Result<Vec<&'a str>, Problem> {
+    let mut order = vec![];
+    visit(riddles, root, &mut HashMap::new(), &mut order, None)?;
+    Ok(order)
+}
+
+// everything wrong with the riddles, in the order a walk through the monkeys by name finds it
+fn validate(riddles: &HashMap<String, Riddle>, root: &str) -> Vec<Problem> {
+    if !riddles.contains_key(root) {
+        return vec![Problem::Missing(root.to_string())];
+    }
+    let mut names = riddles.keys().map(String::as_str).collect::<Vec<_>>();
+    names.sort();
+
+    let mut problems = vec![];
+    let mut marks = HashMap::new();
+    for &name in &names {
+        visit(riddles, name, &mut marks, &mut vec![], Some(&mut problems))
+            .expect("every name is defined");
+    }
+
+    let mut reachable = HashSet::from([root]);
+    let mut queue = vec![root];
+    while let Some(job) = queue.pop() {
+        for child in riddles.get(job).map_or(vec![], children) {
+            if reachable.insert(child) {
+                queue.push(child);
+            }
+        }
+    }
+    problems.extend(
+        names
+            .into_iter()
+            .filter(|name| !reachable.contains(name))
+            .map(|name| Problem::Unreachable(name.to_string())),
+    );
+    problems
+}
+
+// the value of every monkey in order, skipping the ones in skip and whatever waits on them
+fn values<'a, N: Numeric>(
+    riddles: &HashMap<String, Riddle>,
+    order: &[&'a str],
+    skip: &HashSet<&str>,
+) -> Result<HashMap<&'a str, N>, EvalError> {
+    let mut values = HashMap::new();
+    for &job in order {
+        if skip.contains(job) {
+            continue;
+        }
+        let value = match &riddles[job] {
+            Riddle::Num(num) => Ok(N::from_i64(*num)),
+            riddle => {
+                let (left, right) = operands(riddle).unwrap();
+                let (Some(&left), Some(&right)) = (values.get(left), values.get(right)) else {
+                    continue;
+                };
+                match riddle {
+                    Riddle::Add(..) => N::add(left, right),
+                    Riddle::Multiply(..) => N::multiply(left, right),
+                    Riddle::Divide(..) => N::divide(left, right),
+                    Riddle::Subtract(..) => N::subtract(left, right),
+                    Riddle::Num(_) => unreachable!(),
+                }
+            }
+        };
+        let value = value.map_err(|error| EvalError::Arithmetic {
+            job: job.to_string(),
+            error,
+        })?;
+        values.insert(job, value);
+    }
+    Ok(values)
+}
+
+// evaluates job in whichever number type the caller picks
+fn evaluate<N: Numeric>(riddles: &HashMap<String, Riddle>, job: &str) -> Result<N, EvalError> {
+    let order = topological_order(riddles, job).map_err(EvalError::Invalid)?;
+    let values = values::<N>(riddles, &order, &HashSet::new())?;
+    Ok(values[job])
+}
+
+fn traverse(riddles: &HashMap<String, Riddle>, job: &str) -> i64 {
+    evaluate::<i64>(riddles, job).expect("evaluation failed")
+}
+
+// job as a formula, with the monkeys in keep left as names
+fn to_infix(
+    riddles: &HashMap<String, Riddle>,
+    job: &str,
+    keep: &[&str],
+) -> Result<String, Problem> {
+    // precedence of each monkey's formula, leaves bind tightest
+    let mut formulas: HashMap<&str, (String, u8)> = HashMap::new();
+    for job in topological_order(riddles, job)? {
+        let riddle = &riddles[job];
+        let formula = match (riddle, keep.contains(&job)) {
+            (_, true) => (job.to_string(), 3),
+            (Riddle::Num(num), _) => (num.to_string(), 3),
+            _ => {
+                let (left, right) = operands(riddle).unwrap();
+                let (op, precedence) = match riddle {
+                    Riddle::Add(..) => ('+', 1),
+                    Riddle::Subtract(..) => ('-', 1),
+                    Riddle::Multiply(..) => ('*', 2),
+                    Riddle::Divide(..) => ('/', 2),
+                    Riddle::Num(_) => unreachable!(),
+                };
+                // a right operand of the same precedence keeps its brackets, truncating
+                // division makes even a * (b / c) differ from a * b / c
+                let wrap = |(formula, inner): &(String, u8), right: bool| {
+                    if *inner < precedence || (right && *inner == precedence) {
+                        format!("({})", formula)
+                    } else {
+                        formula.clone()
+                    }
+                };
+                let left = wrap(&formulas[left], false);
+                let right = wrap(&formulas[right], true);
+                (format!("{} {} {}", left, op, right), precedence)
+            }
+        };
+        formulas.insert(job, formula);
+    }
+    Ok(formulas.remove(job).unwrap().0)
+}
+
+#[derive(Debug, PartialEq)]
+enum SolveError {
+    // the job that depends on the unknown through both operands
+    BothSides(String),
+    // the job whose inverted division or multiplication leaves a remainder
+    Inexact(String),
+    // the job whose inverted operation does not fit in an i64
+    Overflow(String),
+    NotFound(String),
+    // root is a number, so there are no two sides to set equal
+    NotAnEquation(String),
+    Eval(EvalError),
+}
+
+// treats the operands of root as the two sides of an equation and solves it for unknown,
+// undoing one operation per step on the way down to it
+fn solve(riddles: &HashMap<String, Riddle>, root: &str, unknown: &str) -> Result<i64, SolveError> {
+    let order = topological_order(riddles, root)
+        .map_err(|problem| SolveError::Eval(EvalError::Invalid(problem)))?;
+    let Some((left, right)) = operands(&riddles[root]) else {
+        return Err(SolveError::NotAnEquation(root.to_string()));
+    };
+    let mut dependent = HashSet::from([unknown]);
+    for &job in &order {
+        if children(&riddles[job])
+            .iter()
+            .any(|c| dependent.contains(c))
+        {
+            dependent.insert(job);
+        }
+    }
+    if !dependent.contains(root) {
+        return Err(SolveError::NotFound(unknown.to_string()));
+    }
+    // the known side has to come out exact for the answer to be
+    let known = values::<Strict>(riddles, &order, &dependent).map_err(|error| match error {
+        EvalError::Arithmetic {
+            job,
+            error: ArithmeticError::Inexact,
+        } => SolveError::Inexact(job),
+        error => SolveError::Eval(error),
+    })?;
+    let known = known
+        .into_iter()
+        .map(|(job, Strict(value))| (job, value))
+        .collect::<HashMap<_, _>>();
+
+    // the sides of root are equal, so the unknown side must be worth the known one
+    let (mut job, mut target) = match (dependent.contains(left), dependent.contains(right)) {
+        (true, true) => return Err(SolveError::BothSides(root.to_string())),
+        (true, false) => (left, known[right]),
+        (false, true) => (right, known[left]),
+        (false, false) => unreachable!(),
+    };
+    while job != unknown {
+        let riddle = &riddles[job];
+        let (left, right) = operands(riddle).unwrap();
+        let checked = |result: Result<i64, ArithmeticError>| {
+            result.map_err(|_| SolveError::Overflow(job.to_string()))
+        };
+        let exact = |num: i64, den: i64| match num.checked_rem(den) {
+            _ if den == 0 => Err(SolveError::Inexact(job.to_string())),
+            Some(0) => checked(num.divide(den)),
+            Some(_) => Err(SolveError::Inexact(job.to_string())),
+            None => Err(SolveError::Overflow(job.to_string())),
+        };
+        (job, target) = match (dependent.contains(left), dependent.contains(right)) {
+            (true, true) => return Err(SolveError::BothSides(job.to_string())),
+            // x op k = target
+            (true, false) => {
+                let known = known[right];
+                let target = match riddle {
+                    Riddle::Add(..) => checked(target.subtract(known))?,
+                    Riddle::Subtract(..) => checked(target.add(known))?,
+                    Riddle::Multiply(..) => exact(target, known)?,
+                    Riddle::Divide(..) => checked(target.multiply(known))?,
+                    Riddle::Num(_) => unreachable!(),
+                };
+                (left, target)
+            }
+            // k op x = target
+            (false, true) => {
+                let known = known[left];
+                let target = match riddle {
+                    Riddle::Add(..) => checked(target.subtract(known))?,
+                    Riddle::Subtract(..) => checked(known.subtract(target))?,
+                    Riddle::Multiply(..) => exact(target, known)?,
+                    Riddle::Divide(..) => exact(known, target)?,
+                    Riddle::Num(_) => unreachable!(),
+                };
+                (right, target)
+            }
+            (false, false) => unreachable!(),
+        };
+    }
+    Ok(target)
 }
 
 fn day21a(path: &str) -> i64 {
@@ -109,22 +559,10 @@ fn day21a(path: &str) -> i64 {
     traverse(&riddles, "root")
 }
 
-fn day21b(path: &str, lower: i64, upper: i64) -> i64 {
+fn day21b(path: &str) -> i64 {
     let content = fs::read_to_string(path).expect("file not found");
-    let (_, mut riddles) = parse(&content).unwrap();
-    let (left, right) = match &riddles["root"] {
-        Riddle::Add(left, right) => (left, right),
-        Riddle::Multiply(left, right) => (left, right),
-        Riddle::Divide(left, right) => (left, right),
-        Riddle::Subtract(left, right) => (left, right),
-        _ => todo!(),
-    };
-    riddles.insert(
-        "root".to_string(),
-        Riddle::Equal(left.to_string(), right.to_string()),
-    );
-
-    binary_search(&mut riddles, "root", lower, upper)
+    let (_, riddles) = parse(&content).unwrap();
+    solve(&riddles, "root", "humn").expect("no single solution")
 }
 
 #[cfg(test)]
@@ -140,7 +578,7 @@ mod tests {
 
     #[test]
     fn solve_riddle_with_input() {
-        let actual = day21b("./data/day21.txt", 1000, 1);
+        let actual = day21b("./data/day21.txt");
         assert_eq!(actual, 301);
     }
 
@@ -152,11 +590,222 @@ mod tests {
 
     #[test]
     fn solve_riddle_part_b() {
-        let actual = day21b(
-            "./data/day21final.txt",
-            1_000_000_000_000,
-            30_000_000_000_000,
-        );
+        let actual = day21b("./data/day21final.txt");
         assert_eq!(actual, 3560324848168);
     }
+
+    #[test]
+    fn solve_rejects_unsolvable_riddles() {
+        let (_, riddles) = parse("root: aaaa + humn\naaaa: humn * bbbb\nbbbb: 2\nhumn: 5")
+            .expect("parsing failed");
+        assert_eq!(
+            solve(&riddles, "root", "humn"),
+            Err(SolveError::BothSides("root".to_string()))
+        );
+
+        let (_, riddles) = parse("root: aaaa + bbbb\naaaa: humn * cccc\nbbbb: 7\ncccc: 2\nhumn: 5")
+            .expect("parsing failed");
+        assert_eq!(
+            solve(&riddles, "root", "humn"),
+            Err(SolveError::Inexact("aaaa".to_string()))
+        );
+        assert_eq!(
+            solve(&riddles, "root", "zzzz"),
+            Err(SolveError::NotFound("zzzz".to_string()))
+        );
+
+        // humn would have to be 3.5
+        let (_, riddles) = parse(
+            "root: aaaa + bbbb\naaaa: humn * eeee\neeee: 1\nbbbb: cccc / dddd\ncccc: 7\ndddd: 2\nhumn: 5",
+        )
+        .expect("parsing failed");
+        assert_eq!(
+            solve(&riddles, "root", "humn"),
+            Err(SolveError::Inexact("bbbb".to_string()))
+        );
+
+        let (_, riddles) = parse("humn: 5").expect("parsing failed");
+        assert_eq!(
+            solve(&riddles, "humn", "humn"),
+            Err(SolveError::NotAnEquation("humn".to_string()))
+        );
+    }
+
+    #[test]
+    fn solve_reports_overflow() {
+        let (_, riddles) = parse(
+            "root: aaaa + bbbb\naaaa: humn / cccc\ncccc: 10\nbbbb: 9000000000000000000\nhumn: 5",
+        )
+        .expect("parsing failed");
+        assert_eq!(
+            solve(&riddles, "root", "humn"),
+            Err(SolveError::Overflow("aaaa".to_string()))
+        );
+
+        let (_, riddles) = parse(
+            "root: aaaa + bbbb\naaaa: cccc - humn\ncccc: 9000000000000000000\nbbbb: 0\nhumn: 5",
+        )
+        .expect("parsing failed");
+        assert_eq!(solve(&riddles, "root", "humn"), Ok(9000000000000000000));
+    }
+
+    #[test]
+    fn solve_checks_out() {
+        let content = fs::read_to_string("./data/day21final.txt").expect("file not found");
+        let (_, mut riddles) = parse(&content).expect("parsing failed");
+        let humn = solve(&riddles, "root", "humn").unwrap();
+        riddles.insert("humn".to_string(), Riddle::Num(humn));
+        let (left, right) = operands(&riddles["root"]).unwrap();
+        assert_eq!(traverse(&riddles, left), traverse(&riddles, right));
+    }
+
+    #[test]
+    fn evaluate_with_backends() {
+        let (_, riddles) = parse("root: aaaa * bbbb\naaaa: cccc / dddd\nbbbb: 4\ncccc: 7\ndddd: 2")
+            .expect("parsing failed");
+        assert_eq!(evaluate::<i64>(&riddles, "root"), Ok(12));
+        assert_eq!(
+            evaluate::<Rational>(&riddles, "root").map(Rational::to_integer),
+            Ok(Some(14))
+        );
+        assert_eq!(
+            evaluate::<Rational>(&riddles, "aaaa"),
+            Ok(Rational { num: 7, den: 2 })
+        );
+        assert_eq!(
+            evaluate::<Strict>(&riddles, "root"),
+            Err(EvalError::Arithmetic {
+                job: "aaaa".to_string(),
+                error: ArithmeticError::Inexact
+            })
+        );
+    }
+
+    #[test]
+    fn evaluate_reports_overflow_and_division_by_zero() {
+        let (_, riddles) = parse(
+            "root: aaaa * aaaa\naaaa: bbbb * bbbb\nbbbb: 9000000000\nzero: bbbb / cccc\ncccc: 0",
+        )
+        .expect("parsing failed");
+        let overflow = Err(EvalError::Arithmetic {
+            job: "aaaa".to_string(),
+            error: ArithmeticError::Overflow,
+        });
+        assert_eq!(evaluate::<i64>(&riddles, "root"), overflow);
+        assert_eq!(
+            evaluate::<Rational>(&riddles, "root"),
+            Err(EvalError::Arithmetic {
+                job: "root".to_string(),
+                error: ArithmeticError::Overflow,
+            })
+        );
+        assert_eq!(
+            evaluate::<Strict>(&riddles, "zero"),
+            Err(EvalError::Arithmetic {
+                job: "zero".to_string(),
+                error: ArithmeticError::DivideByZero,
+            })
+        );
+    }
+
+    #[test]
+    fn rational_matches_integers_on_input() {
+        let content = fs::read_to_string("./data/day21final.txt").expect("file not found");
+        let (_, riddles) = parse(&content).expect("parsing failed");
+        let exact = evaluate::<Rational>(&riddles, "root").unwrap();
+        assert_eq!(exact.to_integer(), Some(85616733059734));
+        assert_eq!(
+            evaluate::<Strict>(&riddles, "root"),
+            Ok(Strict(85616733059734))
+        );
+    }
+
+    #[test]
+    fn validate_riddles() {
+        let content = fs::read_to_string("./data/day21.txt").expect("file not found");
+        let (_, riddles) = parse(&content).expect("parsing failed");
+        assert_eq!(validate(&riddles, "root"), vec![]);
+
+        let (_, riddles) = parse(
+            "root: aaaa + bbbb\naaaa: cccc * dddd\ncccc: aaaa - eeee\neeee: 1\nbbbb: 2\nzzzz: 3",
+        )
+        .expect("parsing failed");
+        assert_eq!(
+            validate(&riddles, "root"),
+            vec![
+                Problem::Cycle(vec!["aaaa".to_string(), "cccc".to_string()]),
+                Problem::Undefined {
+                    job: "aaaa".to_string(),
+                    name: "dddd".to_string()
+                },
+                Problem::Unreachable("zzzz".to_string()),
+            ]
+        );
+        assert_eq!(
+            evaluate::<i64>(&riddles, "root"),
+            Err(EvalError::Invalid(Problem::Cycle(vec![
+                "aaaa".to_string(),
+                "cccc".to_string()
+            ])))
+        );
+        assert_eq!(
+            evaluate::<i64>(&riddles, "yyyy"),
+            Err(EvalError::Invalid(Problem::Missing("yyyy".to_string())))
+        );
+
+        // an undefined operand before the one leading round the cycle
+        let (_, riddles) =
+            parse("root: aaaa + bbbb\naaaa: dddd * cccc\ncccc: aaaa - eeee\neeee: 1\nbbbb: 2")
+                .expect("parsing failed");
+        assert_eq!(
+            validate(&riddles, "root"),
+            vec![
+                Problem::Undefined {
+                    job: "aaaa".to_string(),
+                    name: "dddd".to_string()
+                },
+                Problem::Cycle(vec!["aaaa".to_string(), "cccc".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn evaluate_deep_chains() {
+        let mut riddles = HashMap::new();
+        riddles.insert("m0".to_string(), Riddle::Num(1));
+        for i in 1..100_000 {
+            riddles.insert(
+                format!("m{}", i),
+                Riddle::Add(format!("m{}", i - 1), "m0".to_string()),
+            );
+        }
+        assert_eq!(evaluate::<i64>(&riddles, "m99999"), Ok(100_000));
+    }
+
+    #[test]
+    fn print_infix() {
+        let content = fs::read_to_string("./data/day21.txt").expect("file not found");
+        let (_, riddles) = parse(&content).expect("parsing failed");
+        assert_eq!(
+            to_infix(&riddles, "pppw", &[]),
+            Ok("(4 + 2 * (5 - 3)) / 4".to_string())
+        );
+        assert_eq!(
+            to_infix(&riddles, "root", &["humn"]),
+            Ok("(4 + 2 * (humn - 3)) / 4 + (32 - 2) * 5".to_string())
+        );
+
+        let (_, riddles) = parse(
+            "root: aaaa * bbbb\naaaa: 2\nbbbb: cccc / dddd\ncccc: 7\ndddd: 2\neeee: aaaa - ffff\nffff: aaaa + cccc",
+        )
+        .expect("parsing failed");
+        assert_eq!(
+            to_infix(&riddles, "root", &[]),
+            Ok("2 * (7 / 2)".to_string())
+        );
+        assert_eq!(
+            to_infix(&riddles, "eeee", &[]),
+            Ok("2 - (2 + 7)".to_string())
+        );
+    }
 }
@@ -1,9 +1,6 @@
 #![allow(dead_code)]
 
-use std::{
-    collections::{HashMap, HashSet},
-    fs,
-};
+use std::{collections::HashMap, fs};
 
 use nom::{
     branch::alt,
@@ -15,30 +12,16 @@ use nom::{
 };
 
 type Coord = (usize, usize);
-
-#[derive(Debug, PartialEq)]
-struct Coord3D {
-    x: usize,
-    y: usize,
-    z: usize,
-    plane: CubeSide,
-}
+type Vec3 = [i32; 3];
 
 struct Person {
     facing: Direction,
     position: Coord,
     jungle: HashMap<Coord, Pixel>,
-    is_cube: bool,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-enum CubeSide {
-    Top(usize),
-    Bottom(usize),
-    Left(usize),
-    Right(usize),
-    Front(usize),
-    Back(usize),
+    extents: Extents,
+    cube: Option<Cube>,
+    // every cell stood on, with the facing at the time
+    trace: Vec<(Coord, Direction)>,
 }
 
 #[derive(Debug, Clone)]
@@ -55,139 +38,301 @@ enum Move {
     Forward(i64),
 }
 
-#[derive(Debug)]
+// first and last cell on the map of every row and column, indexed by y and x
+struct Extents {
+    rows: Vec<Coord>,
+    columns: Vec<Coord>,
+}
+
+impl Extents {
+    fn new(jungle: &HashMap<Coord, Pixel>) -> Self {
+        let width = jungle.keys().map(|(x, _)| *x).max().unwrap_or(0);
+        let height = jungle.keys().map(|(_, y)| *y).max().unwrap_or(0);
+        let mut rows = vec![(usize::MAX, 0); height + 1];
+        let mut columns = vec![(usize::MAX, 0); width + 1];
+        for (&(x, y), pixel) in jungle {
+            if matches!(pixel, Pixel::Void) {
+                continue;
+            }
+            rows[y] = (rows[y].0.min(x), rows[y].1.max(x));
+            columns[x] = (columns[x].0.min(y), columns[x].1.max(y));
+        }
+        Extents { rows, columns }
+    }
+}
+
+// in the order the password scores them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Direction {
-    North,
-    South,
     East,
+    South,
     West,
+    North,
 }
 
-impl Person {
-    fn turn(&mut self, towards: &Move) {
-        match (&self.facing, towards) {
-            (Direction::North, Move::Left) => self.facing = Direction::West,
-            (Direction::North, Move::Right) => self.facing = Direction::East,
-            (Direction::East, Move::Left) => self.facing = Direction::North,
-            (Direction::East, Move::Right) => self.facing = Direction::South,
-            (Direction::South, Move::Left) => self.facing = Direction::East,
-            (Direction::South, Move::Right) => self.facing = Direction::West,
-            (Direction::West, Move::Left) => self.facing = Direction::South,
-            (Direction::West, Move::Right) => self.facing = Direction::North,
-            (_, _) => panic!("Unknown turn: {:?} {:?}", self.facing, towards),
+const DIRECTIONS: [Direction; 4] = [
+    Direction::East,
+    Direction::South,
+    Direction::West,
+    Direction::North,
+];
+
+impl Direction {
+    fn opposite(self) -> Self {
+        DIRECTIONS[(self as usize + 2) % 4]
+    }
+
+    fn marker(self) -> char {
+        match self {
+            Direction::East => '>',
+            Direction::South => 'v',
+            Direction::West => '<',
+            Direction::North => '^',
+        }
+    }
+}
+
+// a face of the folded cube: where its tile sits on the map, and which way the map's x and
+// y axes and the face's outward normal point in space
+#[derive(Debug, Clone, Copy)]
+struct Face {
+    tile: Coord,
+    right: Vec3,
+    down: Vec3,
+    normal: Vec3,
+}
+
+fn neg([x, y, z]: Vec3) -> Vec3 {
+    [-x, -y, -z]
+}
+
+fn add([a, b, c]: Vec3, [x, y, z]: Vec3) -> Vec3 {
+    [a + x, b + y, c + z]
+}
+
+impl Face {
+    // the face reached by rolling the cube over the edge on one side
+    fn roll(&self, side: Direction) -> Face {
+        let Face {
+            tile: (x, y),
+            right,
+            down,
+            normal,
+        } = *self;
+        match side {
+            Direction::East => Face {
+                tile: (x + 1, y),
+                right: neg(normal),
+                down,
+                normal: right,
+            },
+            Direction::South => Face {
+                tile: (x, y + 1),
+                right,
+                down: neg(normal),
+                normal: down,
+            },
+            Direction::West => Face {
+                tile: (x.wrapping_sub(1), y),
+                right: normal,
+                down,
+                normal: neg(right),
+            },
+            Direction::North => Face {
+                tile: (x, y.wrapping_sub(1)),
+                right,
+                down: normal,
+                normal: neg(down),
+            },
         }
     }
 
-    fn wrap(&mut self, (x, y): &Coord) {
-        let coord = match self.facing {
-            Direction::North => self
-                .jungle
-                .iter()
-                .filter(|((xx, _), _)| *xx == *x)
-                .filter_map(|(coord, pixel)| match pixel {
-                    Pixel::Open | Pixel::Wall => Some(coord),
-                    _ => None,
-                })
-                .max_by_key(|(_, y)| y),
-            Direction::East => self
-                .jungle
-                .iter()
-                .filter(|((_, yy), _)| *yy == *y)
-                .filter_map(|(coord, pixel)| match pixel {
-                    Pixel::Open | Pixel::Wall => Some(coord),
-                    _ => None,
-                })
-                .min_by_key(|(x, _)| x),
-            Direction::South => self
-                .jungle
-                .iter()
-                .filter(|((xx, _), _)| *xx == *x)
-                .filter_map(|(coord, pixel)| match pixel {
-                    Pixel::Open | Pixel::Wall => Some(coord),
-                    _ => None,
-                })
-                .min_by_key(|(_, y)| y),
-            Direction::West => self
-                .jungle
-                .iter()
-                .filter(|((_, yy), _)| *yy == *y)
-                .filter_map(|(coord, pixel)| match pixel {
-                    Pixel::Open | Pixel::Wall => Some(coord),
-                    _ => None,
-                })
-                .max_by_key(|(x, _)| x),
-        };
+    // the outward direction of the edge on one side
+    fn towards(&self, side: Direction) -> Vec3 {
+        match side {
+            Direction::East => self.right,
+            Direction::South => self.down,
+            Direction::West => neg(self.right),
+            Direction::North => neg(self.down),
+        }
+    }
 
-        match self.jungle[coord.unwrap()] {
-            Pixel::Open => self.position = *coord.unwrap(),
-            _ => (),
+    // the corners of the edge on one side, ordered the way the map counts along it
+    fn corners(&self, side: Direction) -> (Vec3, Vec3) {
+        let corner = |right: Vec3, down: Vec3| add(self.normal, add(right, down));
+        let (left, up) = (neg(self.right), neg(self.down));
+        match side {
+            Direction::East => (corner(self.right, up), corner(self.right, self.down)),
+            Direction::South => (corner(left, self.down), corner(self.right, self.down)),
+            Direction::West => (corner(left, up), corner(left, self.down)),
+            Direction::North => (corner(left, up), corner(self.right, up)),
         }
     }
+}
 
-    fn wrap_cube(&mut self, (x, y): &Coord, cube: &HashMap<Coord, Coord3D>) {
-        if let Some(result) = cube.get(&(*x, *y)) {
-            let others = cube
-                .iter()
-                .filter(|(_, d3)| d3.x == result.x && d3.y == result.y && d3.z == result.z)
-                .filter(|(coord, _)| **coord != (*x, *y))
-                .map(|(x, _)| x)
-                .collect::<Vec<_>>();
-            if others.len() == 1 {
-                match self.jungle.get(others[0]) {
-                    Some(Pixel::Open) => {
-                        self.position = *others[0];
-                        self.facing = get_direction(&self.facing, &result.plane);
-                    }
-                    _ => (),
-                }
-            } else {
-                let plane = match (&cube.get(&(*x, *y)).unwrap().plane, &self.facing) {
-                    (CubeSide::Top(_), Direction::North) => CubeSide::Back(0),
-                    (CubeSide::Top(_), Direction::East) => CubeSide::Right(0),
-                    (CubeSide::Top(_), Direction::South) => CubeSide::Front(0),
-                    (CubeSide::Top(_), Direction::West) => CubeSide::Left(0),
-                    (CubeSide::Bottom(_), Direction::North) => CubeSide::Left(0),
-                    (CubeSide::Bottom(_), Direction::East) => CubeSide::Front(0),
-                    (CubeSide::Bottom(_), Direction::South) => CubeSide::Right(0),
-                    (CubeSide::Bottom(_), Direction::West) => CubeSide::Back(0),
-                    (CubeSide::Left(_), Direction::North) => CubeSide::Top(0),
-                    (CubeSide::Left(_), Direction::East) => CubeSide::Front(0),
-                    (CubeSide::Left(_), Direction::South) => CubeSide::Bottom(0),
-                    (CubeSide::Left(_), Direction::West) => CubeSide::Back(0),
-                    (CubeSide::Right(_), Direction::North) => CubeSide::Bottom(0),
-                    (CubeSide::Right(_), Direction::East) => CubeSide::Front(0),
-                    (CubeSide::Right(_), Direction::South) => CubeSide::Top(0),
-                    (CubeSide::Right(_), Direction::West) => CubeSide::Back(0),
-                    (CubeSide::Front(_), Direction::North) => CubeSide::Top(0),
-                    (CubeSide::Front(_), Direction::East) => CubeSide::Right(0),
-                    (CubeSide::Front(_), Direction::South) => CubeSide::Bottom(0),
-                    (CubeSide::Front(_), Direction::West) => CubeSide::Left(0),
-                    (CubeSide::Back(_), Direction::North) => CubeSide::Bottom(0),
-                    (CubeSide::Back(_), Direction::East) => CubeSide::Right(0),
-                    (CubeSide::Back(_), Direction::South) => CubeSide::Top(0),
-                    (CubeSide::Back(_), Direction::West) => CubeSide::Left(0),
-                };
-                let with_plane = cube
+// where walking off a face lands: the next face, the side it is entered through, and whether
+// the position along the edge counts the other way round
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Edge {
+    face: usize,
+    side: Direction,
+    reversed: bool,
+}
+
+#[derive(Debug)]
+struct Cube {
+    size: usize,
+    faces: Vec<Face>,
+    // indexed by face, then by the direction it is left in
+    edges: Vec<[Edge; 4]>,
+}
+
+#[derive(Debug, PartialEq)]
+enum FoldError {
+    // the number of tiles on the map does not make six square faces
+    Area(usize),
+    // the tiles do not split into six full faces
+    Faces(usize),
+    Disconnected,
+    // two faces end up on the same side of the cube
+    Overlap(Coord, Coord),
+}
+
+// folds the map into a cube, working out which face edges meet from the net alone
+fn fold(jungle: &HashMap<Coord, Pixel>) -> Result<Cube, FoldError> {
+    let cells = jungle
+        .iter()
+        .filter(|(_, pixel)| !matches!(pixel, Pixel::Void))
+        .map(|(coord, _)| *coord)
+        .collect::<Vec<_>>();
+    let size = (1..=cells.len())
+        .take_while(|n| n * n * 6 <= cells.len())
+        .last()
+        .filter(|n| n * n * 6 == cells.len())
+        .ok_or(FoldError::Area(cells.len()))?;
+
+    let mut tiles = cells
+        .iter()
+        .map(|(x, y)| ((x - 1) / size, (y - 1) / size))
+        .collect::<Vec<_>>();
+    tiles.sort_by_key(|&(x, y)| (y, x));
+    tiles.dedup();
+    if tiles.len() != 6 {
+        return Err(FoldError::Faces(tiles.len()));
+    }
+
+    // roll a cube across the net, starting on the first face with the map lying flat
+    let mut faces = vec![Face {
+        tile: tiles[0],
+        right: [1, 0, 0],
+        down: [0, 1, 0],
+        normal: [0, 0, 1],
+    }];
+    let mut queue = vec![faces[0]];
+    while let Some(face) = queue.pop() {
+        for side in DIRECTIONS {
+            let next = face.roll(side);
+            if tiles.contains(&next.tile) && faces.iter().all(|f| f.tile != next.tile) {
+                faces.push(next);
+                queue.push(next);
+            }
+        }
+    }
+    if faces.len() != 6 {
+        return Err(FoldError::Disconnected);
+    }
+    faces.sort_by_key(|face| (face.tile.1, face.tile.0));
+    for (i, face) in faces.iter().enumerate() {
+        if let Some(other) = faces[i + 1..].iter().find(|f| f.normal == face.normal) {
+            return Err(FoldError::Overlap(face.tile, other.tile));
+        }
+    }
+
+    let edges = faces
+        .iter()
+        .map(|face| {
+            DIRECTIONS.map(|side| {
+                let (idx, next) = faces
                     .iter()
-                    .filter(|(_, d3)| {
-                        d3.x == result.x
-                            && d3.y == result.y
-                            && d3.z == result.z
-                            && is_same_plane(&plane, &d3.plane)
-                    })
-                    .filter(|(coord, _)| **coord != (*x, *y))
-                    .map(|(x, _)| x)
-                    .collect::<Vec<_>>();
-                if with_plane.len() != 1 {
-                    panic!("No unqiue cube found");
+                    .enumerate()
+                    .find(|(_, f)| f.normal == face.towards(side))
+                    .unwrap();
+                let entry = DIRECTIONS
+                    .into_iter()
+                    .find(|&s| next.towards(s) == face.normal)
+                    .unwrap();
+                Edge {
+                    face: idx,
+                    side: entry,
+                    reversed: face.corners(side).0 != next.corners(entry).0,
                 }
-                self.position = *with_plane[0];
-                self.facing = get_direction(&self.facing, &result.plane);
-            }
+            })
+        })
+        .collect();
+
+    Ok(Cube { size, faces, edges })
+}
+
+impl Cube {
+    fn face_of(&self, (x, y): Coord) -> usize {
+        let tile = ((x - 1) / self.size, (y - 1) / self.size);
+        self.faces.iter().position(|f| f.tile == tile).unwrap()
+    }
+
+    // the cell and facing after stepping off the edge of the face at position
+    fn step_over(&self, (x, y): Coord, facing: Direction) -> (Coord, Direction) {
+        let n = self.size;
+        let edge = self.edges[self.face_of((x, y))][facing as usize];
+        let along = match facing {
+            Direction::East | Direction::West => (y - 1) % n,
+            Direction::South | Direction::North => (x - 1) % n,
+        };
+        let along = if edge.reversed { n - 1 - along } else { along };
+        let (dx, dy) = match edge.side {
+            Direction::East => (n - 1, along),
+            Direction::South => (along, n - 1),
+            Direction::West => (0, along),
+            Direction::North => (along, 0),
+        };
+        let (tx, ty) = self.faces[edge.face].tile;
+        ((tx * n + dx + 1, ty * n + dy + 1), edge.side.opposite())
+    }
+}
+
+impl Person {
+    fn turn(&mut self, towards: &Move) {
+        let turn = match towards {
+            Move::Right => 1,
+            Move::Left => 3,
+            Move::Forward(_) => panic!("Unknown turn: {:?} {:?}", self.facing, towards),
+        };
+        self.facing = DIRECTIONS[(self.facing as usize + turn) % 4];
+        self.trace.push((self.position, self.facing));
+    }
+
+    fn wrap(&mut self) {
+        let (x, y) = self.position;
+        let coord = match self.facing {
+            Direction::North => (x, self.extents.columns[x].1),
+            Direction::East => (self.extents.rows[y].0, y),
+            Direction::South => (x, self.extents.columns[x].0),
+            Direction::West => (self.extents.rows[y].1, y),
+        };
+        if let Some(Pixel::Open) = self.jungle.get(&coord) {
+            self.position = coord;
         }
     }
 
-    fn walk(&mut self, cube: &HashMap<Coord, Coord3D>) {
+    fn wrap_cube(&mut self) {
+        let cube = self.cube.as_ref().unwrap();
+        let (next, facing) = cube.step_over(self.position, self.facing);
+        if let Some(Pixel::Open) = self.jungle.get(&next) {
+            self.position = next;
+            self.facing = facing;
+        }
+    }
+
+    fn walk(&mut self) {
         let (x, y) = self.position;
         let next = match self.facing {
             Direction::North => (x, y - 1),
@@ -201,290 +346,92 @@ impl Person {
             Some(Pixel::Open) => self.position = next,
             Some(Pixel::Wall) => (),
             _ => {
-                if self.is_cube {
-                    self.wrap_cube(&(x, y), cube);
+                if self.cube.is_some() {
+                    self.wrap_cube();
                 } else {
-                    self.wrap(&next);
+                    self.wrap();
                 }
             }
         }
+        self.trace.push((self.position, self.facing));
     }
 
-    fn password(&self) -> usize {
-        let (column, row) = self.position;
-        match self.facing {
-            Direction::East => row * 1000 + column * 4 + 0,
-            Direction::South => row * 1000 + column * 4 + 1,
-            Direction::West => row * 1000 + column * 4 + 2,
-            Direction::North => row * 1000 + column * 4 + 3,
-        }
+    // the map with the last facing on every visited cell, cells from x, y on, w by h of them
+    fn render_area(&self, (x, y): Coord, (w, h): Coord) -> Vec<String> {
+        let markers = self.trace.iter().copied().collect::<HashMap<_, _>>();
+        (y..y + h)
+            .map(|y| {
+                let row = (x..x + w)
+                    .map(|x| match (markers.get(&(x, y)), self.jungle.get(&(x, y))) {
+                        (Some(facing), _) => facing.marker(),
+                        (None, Some(Pixel::Open)) => '.',
+                        (None, Some(Pixel::Wall)) => '#',
+                        (None, _) => ' ',
+                    })
+                    .collect::<String>();
+                row.trim_end().to_string()
+            })
+            .collect()
     }
 
-    fn cube_size(&self) -> usize {
-        f64::sqrt(
-            (self
-                .jungle
-                .iter()
-                .filter(|(_, pixel)| match pixel {
-                    Pixel::Void => false,
-                    _ => true,
-                })
-                .count()
-                / 6) as f64,
-        ) as usize
-    }
-
-    fn fold(&self) -> HashMap<Coord, Coord3D> {
-        let mut cube = HashMap::new();
-        let width = self.jungle.iter().map(|((x, _), _)| x).max().unwrap();
-        let height = self.jungle.iter().map(|((_, y), _)| y).max().unwrap();
-        let quadrant_size = self.cube_size();
-        let quadrant_width = width / quadrant_size;
-
-        let mut quadrants = (0..12).fold(vec![], |mut acc, _| {
-            acc.push(vec![]);
-            acc
-        });
-
-        for y in 0..*height {
-            for x in 0..*width {
-                let idx = ((x as f64).div_euclid(quadrant_size as f64)
-                    + ((y as f64).div_euclid(quadrant_size as f64) * quadrant_width as f64))
-                    as usize;
-                let coord = (x + 1, y + 1);
-                match self.jungle.get(&coord) {
-                    Some(Pixel::Void) | None => (),
-                    _ => {
-                        quadrants[idx].push(coord);
-                    }
-                }
-            }
-        }
-
-        // find top
-        let sides = (0..6)
-            .map(|x| (x, x + quadrant_width, x + quadrant_width + quadrant_width))
-            .collect::<Vec<_>>();
-
-        for (x, y, z) in sides {
-            if !quadrants[x].is_empty() && !quadrants[y].is_empty() && !quadrants[z].is_empty() {
-                cube.insert(CubeSide::Back(x), quadrants[x].clone());
-                cube.insert(CubeSide::Top(y), quadrants[y].clone());
-                cube.insert(CubeSide::Front(z), quadrants[z].clone());
-                // left
-                if !quadrants[x - 1].is_empty() {
-                    cube.insert(CubeSide::Left(x - 1), quadrants[x - 1].clone());
-                } else if !quadrants[y - 1].is_empty() {
-                    cube.insert(CubeSide::Left(y - 1), quadrants[y - 1].clone());
-                } else if !quadrants[z - 1].is_empty() {
-                    cube.insert(CubeSide::Left(z - 1), quadrants[z - 1].clone());
-                }
-                // right
-                if !quadrants[x + 1].is_empty() {
-                    cube.insert(CubeSide::Right(x + 1), quadrants[x + 1].clone());
-                } else if !quadrants[y + 1].is_empty() {
-                    cube.insert(CubeSide::Right(y + 1), quadrants[y + 1].clone());
-                } else if !quadrants[z + 1].is_empty() {
-                    cube.insert(CubeSide::Right(z + 1), quadrants[z + 1].clone());
-                }
-            }
-        }
+    fn render(&self) -> String {
+        let width = self.jungle.keys().map(|(x, _)| *x).max().unwrap_or(0);
+        let height = self.jungle.keys().map(|(_, y)| *y).max().unwrap_or(0);
+        self.render_area((1, 1), (width, height)).join("\n")
+    }
 
-        let idxs: HashSet<usize> = cube
-            .keys()
-            .map(|side| match side {
-                CubeSide::Back(x) => *x,
-                CubeSide::Top(x) => *x,
-                CubeSide::Front(x) => *x,
-                CubeSide::Left(x) => *x,
-                CubeSide::Right(x) => *x,
-                CubeSide::Bottom(x) => *x,
+    // one picture per face of the cube, in map order
+    fn render_faces(&self) -> Option<Vec<String>> {
+        let cube = self.cube.as_ref()?;
+        let n = cube.size;
+        let faces = cube
+            .faces
+            .iter()
+            .map(|face| {
+                let (tx, ty) = face.tile;
+                self.render_area((tx * n + 1, ty * n + 1), (n, n))
+                    .join("\n")
             })
             .collect();
-        for bottom in 0..12 {
-            if !idxs.contains(&bottom) && !quadrants[bottom].is_empty() {
-                cube.insert(CubeSide::Bottom(bottom), quadrants[bottom].clone());
+        Some(faces)
+    }
+
+    // walking straight comes back round to the same cell and facing after a lap, so long
+    // walks only need one lap plus the remainder
+    fn forward(&mut self, steps: i64) {
+        let (x, y) = self.position;
+        let lap = match (&self.cube, self.facing) {
+            (Some(cube), _) => 4 * cube.size,
+            (None, Direction::East | Direction::West) => {
+                let (first, last) = self.extents.rows[y];
+                last - first + 1
             }
-        }
+            (None, Direction::North | Direction::South) => {
+                let (first, last) = self.extents.columns[x];
+                last - first + 1
+            }
+        } as i64;
+        let steps = if steps < lap {
+            steps
+        } else {
+            lap + steps % lap
+        };
 
-        let top_coords = cube
-            .iter()
-            .filter_map(|(side, coords)| match side {
-                CubeSide::Top(_) => Some(coords.clone()),
-                _ => None,
-            })
-            .flatten()
-            .collect::<Vec<Coord>>();
-
-        let mut transform = HashMap::new();
-        let (min_x, max_x, min_y, max_y) = boundaries(&top_coords);
-
-        for (side, pixels) in cube.iter() {
-            match side {
-                CubeSide::Top(_) => {
-                    for (x, y) in pixels {
-                        transform.insert(
-                            (*x, *y),
-                            Coord3D {
-                                x: *x,
-                                y: *y,
-                                z: quadrant_size,
-                                plane: side.clone(),
-                            },
-                        );
-                    }
-                }
-                CubeSide::Front(_) => {
-                    for (x, y) in pixels {
-                        let new_x = *x;
-                        let new_y = max_y;
-                        let new_z = quadrant_size - ((y - 1) % quadrant_size);
-                        transform.insert(
-                            (*x, *y),
-                            Coord3D {
-                                x: new_x,
-                                y: new_y,
-                                z: new_z,
-                                plane: side.clone(),
-                            },
-                        );
-                    }
-                }
-                CubeSide::Back(_) => {
-                    for (x, y) in pixels {
-                        let new_x = *x;
-                        let new_y = min_y;
-                        let new_z = ((y - 1) % quadrant_size) + 1;
-                        transform.insert(
-                            (*x, *y),
-                            Coord3D {
-                                x: new_x,
-                                y: new_y,
-                                z: new_z,
-                                plane: side.clone(),
-                            },
-                        );
-                    }
-                }
-                CubeSide::Left(_) => {
-                    for (x, y) in pixels {
-                        let new_x = min_x;
-                        let mut new_y = *y;
-                        let mut new_z = ((x - 1) % quadrant_size) + 1;
-                        if *y > max_y {
-                            new_y = min_x + ((*x - 1) % quadrant_size);
-                            new_z = quadrant_size - ((*y - 1) % quadrant_size);
-                        }
-                        transform.insert(
-                            (*x, *y),
-                            Coord3D {
-                                x: new_x,
-                                y: new_y,
-                                z: new_z,
-                                plane: side.clone(),
-                            },
-                        );
-                    }
-                }
-                CubeSide::Right(_) => {
-                    for (x, y) in pixels {
-                        let new_x = max_x;
-                        let mut new_y = max_y - (x - max_x - 1);
-                        let mut new_z = quadrant_size - ((y - 1) % quadrant_size);
-                        if *y < min_y {
-                            new_y = min_x + ((*x - 1) % quadrant_size);
-                            new_z = ((y - 1) % quadrant_size) + 1;
-                        }
-                        transform.insert(
-                            (*x, *y),
-                            Coord3D {
-                                x: new_x,
-                                y: new_y,
-                                z: new_z,
-                                plane: side.clone(),
-                            },
-                        );
-                    }
-                }
-                CubeSide::Bottom(_) => {
-                    for (x, y) in pixels {
-                        let mut new_x = max_x - (*x - 1 % quadrant_size);
-                        let mut new_y = *y;
-                        let new_z = 1;
-                        if *y > max_y {
-                            new_x = min_x + ((*y - 1) % quadrant_size);
-                            new_y = min_x + ((*x - 1) % quadrant_size);
-                        }
-                        transform.insert(
-                            (*x, *y),
-                            Coord3D {
-                                x: new_x,
-                                y: new_y,
-                                z: new_z,
-                                plane: side.clone(),
-                            },
-                        );
-                    }
-                }
+        for _ in 0..steps {
+            let before = (self.position, self.facing);
+            self.walk();
+            if (self.position, self.facing) == before {
+                break;
             }
         }
-
-        transform
-    }
-}
-
-// this will depend on the initial layout of the 2d grid :<
-// currently is it targeted the test input
-// TODO: make this more generic
-fn get_direction(facing: &Direction, plane: &CubeSide) -> Direction {
-    match (plane, facing) {
-        (CubeSide::Top(_), Direction::North) => Direction::North,
-        (CubeSide::Top(_), Direction::South) => Direction::South,
-        (CubeSide::Top(_), Direction::East) => Direction::South,
-        (CubeSide::Top(_), Direction::West) => Direction::North,
-        (CubeSide::Bottom(_), Direction::North) => Direction::North,
-        (CubeSide::Bottom(_), Direction::South) => Direction::South,
-        (CubeSide::Bottom(_), Direction::East) => Direction::North,
-        (CubeSide::Bottom(_), Direction::West) => Direction::South,
-        (CubeSide::Left(_), Direction::North) => Direction::East,
-        (CubeSide::Left(_), Direction::South) => Direction::South,
-        (CubeSide::Left(_), Direction::East) => Direction::East,
-        (CubeSide::Left(_), Direction::West) => Direction::East,
-        (CubeSide::Right(_), Direction::North) => Direction::North,
-        (CubeSide::Right(_), Direction::South) => Direction::West,
-        (CubeSide::Right(_), Direction::East) => Direction::West,
-        (CubeSide::Right(_), Direction::West) => Direction::West,
-        (CubeSide::Front(_), Direction::North) => Direction::North,
-        (CubeSide::Front(_), Direction::South) => Direction::West,
-        (CubeSide::Front(_), Direction::East) => Direction::West,
-        (CubeSide::Front(_), Direction::West) => Direction::West,
-        (CubeSide::Back(_), Direction::North) => Direction::East,
-        (CubeSide::Back(_), Direction::South) => Direction::South,
-        (CubeSide::Back(_), Direction::East) => Direction::East,
-        (CubeSide::Back(_), Direction::West) => Direction::East,
     }
-}
 
-fn is_same_plane(plane: &CubeSide, other: &CubeSide) -> bool {
-    match (plane, other) {
-        (CubeSide::Top(_), CubeSide::Top(_)) => true,
-        (CubeSide::Bottom(_), CubeSide::Bottom(_)) => true,
-        (CubeSide::Left(_), CubeSide::Left(_)) => true,
-        (CubeSide::Right(_), CubeSide::Right(_)) => true,
-        (CubeSide::Front(_), CubeSide::Front(_)) => true,
-        (CubeSide::Back(_), CubeSide::Back(_)) => true,
-        _ => false,
+    fn password(&self) -> usize {
+        let (column, row) = self.position;
+        row * 1000 + column * 4 + self.facing as usize
     }
 }
 
-fn boundaries(coords: &Vec<Coord>) -> (usize, usize, usize, usize) {
-    let min_x = coords.iter().map(|(x, _)| x).min().unwrap().clone();
-    let max_x = coords.iter().map(|(x, _)| x).max().unwrap().clone();
-    let min_y = coords.iter().map(|(_, y)| y).min().unwrap().clone();
-    let max_y = coords.iter().map(|(_, y)| y).max().unwrap().clone();
-    (min_x, max_x, min_y, max_y)
-}
-
 fn parse_jungle(input: &str) -> IResult<&str, Vec<Pixel>> {
     let (input, pixels) = many1(alt((
         complete::char(' '),
@@ -545,7 +492,8 @@ fn parse(input: &str) -> IResult<&str, (Vec<Move>, HashMap<Coord, Pixel>)> {
     Ok((input, (route, jungle)))
 }
 
-fn day22(path: &str, is_cube: bool) -> usize {
+// follows the route in the notes, flat or folded into a cube
+fn walk_route(path: &str, is_cube: bool) -> Person {
     let content = fs::read_to_string(path).expect("file not found");
     let (_, (route, jungle)) = parse(&content).unwrap();
     let start = jungle
@@ -556,23 +504,32 @@ fn day22(path: &str, is_cube: bool) -> usize {
             _ => None,
         })
         .min_by_key(|(x, _)| x)
+        .copied()
         .unwrap();
 
+    let cube = if is_cube {
+        Some(fold(&jungle).expect("not a cube net"))
+    } else {
+        None
+    };
     let mut santa = Person {
         facing: Direction::East,
-        position: start.clone(),
+        position: start,
+        extents: Extents::new(&jungle),
         jungle,
-        is_cube,
+        cube,
+        trace: vec![(start, Direction::East)],
     };
 
-    let cube = santa.fold();
-
     route.iter().for_each(|direction| match direction {
         Move::Left | Move::Right => santa.turn(direction),
-        Move::Forward(steps) => (0..*steps).for_each(|_| santa.walk(&cube)),
+        Move::Forward(steps) => santa.forward(*steps),
     });
+    santa
+}
 
-    santa.password()
+fn day22(path: &str, is_cube: bool) -> usize {
+    walk_route(path, is_cube).password()
 }
 
 #[cfg(test)]
@@ -587,7 +544,6 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
     fn find_cube_password() {
         let actual = day22("./data/day22.txt", true);
         assert_eq!(actual, 5031);
@@ -604,4 +560,207 @@ mod tests {
         let actual = day22("./data/day22final.txt", true);
         assert_eq!(actual, 36540);
     }
+
+    // the 11 nets of a cube, one tile per face
+    const NETS: [&str; 11] = [
+        "#...\n####\n#...",
+        "#...\n####\n.#..",
+        "#...\n####\n..#.",
+        "#...\n####\n...#",
+        ".#..\n####\n.#..",
+        ".#..\n####\n..#.",
+        "##..\n.###\n.#..",
+        "##..\n.###\n..#.",
+        "##..\n.###\n...#",
+        "##..\n.##.\n..##",
+        "###..\n..###",
+    ];
+
+    // every rotation and mirror image of a net
+    fn orientations(net: &str) -> Vec<Vec<Vec<bool>>> {
+        let mut tiles = net
+            .lines()
+            .map(|line| line.chars().map(|c| c == '#').collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut all = vec![];
+        for _ in 0..4 {
+            let rotated = (0..tiles[0].len())
+                .map(|x| (0..tiles.len()).rev().map(|y| tiles[y][x]).collect())
+                .collect::<Vec<Vec<_>>>();
+            let mirrored = rotated
+                .iter()
+                .map(|row| row.iter().rev().copied().collect())
+                .collect();
+            all.push(mirrored);
+            all.push(rotated.clone());
+            tiles = rotated;
+        }
+        all
+    }
+
+    // an open map laid out like the net, with faces of the given size
+    fn synthetic(tiles: &[Vec<bool>], size: usize) -> HashMap<Coord, Pixel> {
+        let mut jungle = HashMap::new();
+        for (ty, row) in tiles.iter().enumerate() {
+            for (tx, &filled) in row.iter().enumerate() {
+                for y in 0..size {
+                    for x in 0..size {
+                        let pixel = if filled { Pixel::Open } else { Pixel::Void };
+                        jungle.insert((tx * size + x + 1, ty * size + y + 1), pixel);
+                    }
+                }
+            }
+        }
+        jungle
+    }
+
+    #[test]
+    fn fold_every_net() {
+        for net in NETS {
+            for tiles in orientations(net) {
+                for size in 1..=4 {
+                    let jungle = synthetic(&tiles, size);
+                    let cube = fold(&jungle).expect("not a cube net");
+
+                    // crossing an edge and turning around crosses straight back
+                    for (face, edges) in cube.edges.iter().enumerate() {
+                        for (side, edge) in DIRECTIONS.into_iter().zip(edges) {
+                            assert_ne!(edge.face, face);
+                            let back = cube.edges[edge.face][edge.side as usize];
+                            assert_eq!((back.face, back.side), (face, side));
+                            assert_eq!(back.reversed, edge.reversed);
+                        }
+                    }
+
+                    // walking straight on an open cube goes round it in 4 faces
+                    let mut santa = Person {
+                        facing: Direction::East,
+                        position: (0, 0),
+                        extents: Extents::new(&jungle),
+                        jungle: jungle.clone(),
+                        cube: Some(cube),
+                        trace: vec![],
+                    };
+                    for (&position, pixel) in &jungle {
+                        if !matches!(pixel, Pixel::Open) {
+                            continue;
+                        }
+                        for facing in DIRECTIONS {
+                            (santa.position, santa.facing) = (position, facing);
+                            for _ in 0..4 * size {
+                                santa.walk();
+                            }
+                            assert_eq!((santa.position, santa.facing), (position, facing));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fold_rejects_other_shapes() {
+        let tiles = |net: &str| {
+            net.lines()
+                .map(|line| line.chars().map(|c| c == '#').collect())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            fold(&synthetic(&tiles("###\n###"), 2)).unwrap_err(),
+            FoldError::Overlap((0, 0), (2, 0))
+        );
+        assert_eq!(
+            fold(&synthetic(&tiles("####\n##.."), 2)).unwrap_err(),
+            FoldError::Overlap((1, 0), (1, 1))
+        );
+        assert_eq!(
+            fold(&synthetic(&tiles("###.\n...#\n..##"), 3)).unwrap_err(),
+            FoldError::Disconnected
+        );
+        assert_eq!(
+            fold(&synthetic(&tiles("#####"), 2)).unwrap_err(),
+            FoldError::Area(20)
+        );
+    }
+
+    #[test]
+    fn render_trace() {
+        let santa = walk_route("./data/day22.txt", false);
+        let expected = [
+            "        >>v#",
+            "        .#v.",
+            "        #.v.",
+            "        ..v.",
+            "...#...v..v#",
+            ">>>v...>#.>>",
+            "..#v...#....",
+            "...>>>>v..#.",
+            "        ...#....",
+            "        .....#..",
+            "        .#......",
+            "        ......#.",
+        ];
+        assert_eq!(santa.render(), expected.join("\n"));
+        assert_eq!(santa.render_faces(), None);
+    }
+
+    #[test]
+    fn render_trace_on_faces() {
+        let santa = walk_route("./data/day22.txt", true);
+        let faces = santa.render_faces().unwrap();
+        assert_eq!(faces.len(), 6);
+        assert_eq!(faces[0], ">>v#\n.#v.\n#.v.\n..v.");
+        // the walk ends on the third face, row 1, column 3, facing up
+        assert_eq!(santa.password(), 5031);
+        assert_eq!(faces[2].lines().next().unwrap().chars().nth(2), Some('^'));
+    }
+
+    #[test]
+    fn forward_a_long_way() {
+        let content = fs::read_to_string("./data/day22.txt").expect("file not found");
+        let (_, (_, jungle)) = parse(&content).expect("parsing failed");
+        let mut santa = Person {
+            facing: Direction::East,
+            position: (9, 4),
+            extents: Extents::new(&jungle),
+            jungle,
+            cube: None,
+            trace: vec![],
+        };
+        // the row has no walls and is 4 cells wide
+        santa.forward(1_000_000_000_001);
+        assert_eq!(santa.position, (10, 4));
+        // the column has a wall in row 2 that stops the walk
+        santa.facing = Direction::North;
+        santa.forward(1_000_000_000_000);
+        assert_eq!(santa.position, (10, 3));
+        // walking into a wall goes nowhere
+        santa.position = (9, 1);
+        santa.facing = Direction::East;
+        santa.forward(1_000_000_000_000);
+        assert_eq!(santa.position, (11, 1));
+
+        let tiles = orientations(NETS[9]).remove(3);
+        let jungle = synthetic(&tiles, 3);
+        let cube = fold(&jungle).ok();
+        let start = *jungle
+            .iter()
+            .find(|(_, pixel)| matches!(pixel, Pixel::Open))
+            .unwrap()
+            .0;
+        let mut santa = Person {
+            facing: Direction::South,
+            position: start,
+            extents: Extents::new(&jungle),
+            jungle,
+            cube,
+            trace: vec![],
+        };
+        santa.forward(5);
+        let expected = (santa.position, santa.facing);
+        santa.position = start;
+        santa.facing = Direction::South;
+        santa.forward(12 * 1_000_000_000 + 5);
+        assert_eq!((santa.position, santa.facing), expected);
+    }
 }